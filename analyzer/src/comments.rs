@@ -0,0 +1,276 @@
+//! Comment extraction from Rust source.
+//!
+//! `syn` keeps doc comments as attributes but drops ordinary comments, so
+//! this is a small lexer that knows just enough Rust (strings, raw strings,
+//! char literals versus lifetimes, nested block comments) to find every
+//! comment and tell the kinds apart.
+
+/// What sort of comment a [`Comment`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...`
+    Line,
+    /// `/// ...` or `/** ... */`
+    OuterDoc,
+    /// `//! ...` or `/*! ... */`
+    InnerDoc,
+    /// `/* ... */`, including snippet banners
+    Block,
+}
+
+impl CommentKind {
+    pub fn is_doc(self) -> bool {
+        matches!(self, CommentKind::OuterDoc | CommentKind::InnerDoc)
+    }
+}
+
+/// One comment with its markers stripped.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub kind: CommentKind,
+    pub text: String,
+    /// Line the comment starts on (1-based).
+    pub line: usize,
+    /// Line the comment ends on; differs from `line` for block comments.
+    pub end_line: usize,
+    /// Whether code precedes the comment on its first line.
+    pub trailing: bool,
+}
+
+/// Returns every comment in `source`, in order.
+pub fn extract(source: &str) -> Vec<Comment> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut comments = Vec::new();
+    let mut line = 1;
+    let mut code_on_line = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\n' => {
+                line += 1;
+                code_on_line = false;
+                i += 1;
+            }
+            '/' if next == Some('/') => {
+                let start = i;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                let raw: String = chars[start..i].iter().collect();
+                let (kind, text) = if raw.starts_with("///") && !raw.starts_with("////") {
+                    (CommentKind::OuterDoc, &raw[3..])
+                } else if let Some(text) = raw.strip_prefix("//!") {
+                    (CommentKind::InnerDoc, text)
+                } else {
+                    (CommentKind::Line, &raw[2..])
+                };
+                comments.push(Comment {
+                    kind,
+                    text: text.trim().to_string(),
+                    line,
+                    end_line: line,
+                    trailing: code_on_line,
+                });
+            }
+            '/' if next == Some('*') => {
+                let start = i;
+                let start_line = line;
+                let mut depth = 0;
+                while i < chars.len() {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        if chars[i] == '\n' {
+                            line += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                let raw: String = chars[start..i].iter().collect();
+                let body = raw
+                    .strip_suffix("*/")
+                    .unwrap_or(&raw)
+                    .get(2..)
+                    .unwrap_or_default();
+                let (kind, text) = if body.starts_with('*') && !body.starts_with("**") {
+                    (CommentKind::OuterDoc, &body[1..])
+                } else if let Some(text) = body.strip_prefix('!') {
+                    (CommentKind::InnerDoc, text)
+                } else {
+                    (CommentKind::Block, body)
+                };
+                comments.push(Comment {
+                    kind,
+                    text: text.trim().to_string(),
+                    line: start_line,
+                    end_line: line,
+                    trailing: code_on_line,
+                });
+            }
+            '"' => {
+                i = skip_string(&chars, i + 1, None, &mut line);
+                code_on_line = true;
+            }
+            'r' | 'b' if is_raw_string_start(&chars, i) => {
+                let mut j = i + 1;
+                if chars[i] == 'b' {
+                    j += 1;
+                }
+                let mut hashes = 0;
+                while chars.get(j) == Some(&'#') {
+                    hashes += 1;
+                    j += 1;
+                }
+                i = skip_string(&chars, j + 1, Some(hashes), &mut line);
+                code_on_line = true;
+            }
+            '\'' => {
+                // A char literal closes within a few chars; a lifetime does not.
+                if next == Some('\\') {
+                    i += 2;
+                    while i < chars.len() && chars[i] != '\'' {
+                        i += 1;
+                    }
+                    i += 1;
+                } else if chars.get(i + 2) == Some(&'\'') {
+                    i += 3;
+                } else {
+                    i += 1;
+                }
+                code_on_line = true;
+            }
+            c => {
+                if !c.is_whitespace() {
+                    code_on_line = true;
+                }
+                i += 1;
+            }
+        }
+    }
+    comments
+}
+
+/// Whether `chars[i..]` opens a raw string: `r"`, `r#"`, `br"`, ...
+fn is_raw_string_start(chars: &[char], i: usize) -> bool {
+    if i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_') {
+        return false;
+    }
+    let mut j = i;
+    if chars[j] == 'b' {
+        j += 1;
+        if chars.get(j) != Some(&'r') {
+            return false;
+        }
+    }
+    j += 1;
+    while chars.get(j) == Some(&'#') {
+        j += 1;
+    }
+    chars.get(j) == Some(&'"')
+}
+
+/// Skips to just past the closing quote and returns the new position. Raw
+/// strings pass `Some(hashes)`: they close at a quote followed by that many
+/// `#`s, and a backslash in them is an ordinary character.
+fn skip_string(chars: &[char], mut i: usize, raw: Option<usize>, line: &mut usize) -> usize {
+    let hashes = raw.unwrap_or(0);
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if raw.is_none() => {
+                if chars.get(i + 1) == Some(&'\n') {
+                    *line += 1;
+                }
+                i += 2;
+            }
+            '"' if (1..=hashes).all(|k| chars.get(i + k) == Some(&'#')) => {
+                return i + 1 + hashes;
+            }
+            c => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                i += 1;
+            }
+        }
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<(CommentKind, String)> {
+        extract(source)
+            .into_iter()
+            .map(|c| (c.kind, c.text))
+            .collect()
+    }
+
+    #[test]
+    fn kinds_are_told_apart() {
+        let source = "//! Crate docs\n/// Item docs\n//// Not a doc\n// Plain\n\
+                      /** Block docs */\n/*! Inner block */\n/* Block */\n/*** Not a doc */\n";
+        let kinds: Vec<CommentKind> = texts(source).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            kinds,
+            [
+                CommentKind::InnerDoc,
+                CommentKind::OuterDoc,
+                CommentKind::Line,
+                CommentKind::Line,
+                CommentKind::OuterDoc,
+                CommentKind::InnerDoc,
+                CommentKind::Block,
+                CommentKind::Block,
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_block_comments_close_at_the_outer_end() {
+        let comments = extract("/* outer /* inner */\nstill outer */ fn main() {} // after\n");
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].text, "outer /* inner */\nstill outer");
+        assert_eq!((comments[0].line, comments[0].end_line), (1, 2));
+        assert_eq!((comments[1].line, comments[1].trailing), (2, true));
+    }
+
+    #[test]
+    fn comment_markers_in_strings_are_not_comments() {
+        let source = "let url = \"https://example.com\"; // real\n\
+                      let s = \"a \\\" // quoted\";\n\
+                      let c = '/'; let d = '\\''; // after chars\n\
+                      fn f<'a>(x: &'a str) {} // after lifetime\n";
+        let found: Vec<String> = texts(source).into_iter().map(|(_, t)| t).collect();
+        assert_eq!(found, ["real", "after chars", "after lifetime"]);
+    }
+
+    #[test]
+    fn backslashes_in_raw_strings_do_not_escape() {
+        // Treated as an escape, the `\"` would keep the string open and
+        // swallow the comment.
+        let source = "let dir = r\"C:\\\"; // windows path\n\
+                      let re = r#\"\\d+\"#; // digits\n\
+                      let bytes = br\"\\\"; // bytes\n";
+        let found: Vec<String> = texts(source).into_iter().map(|(_, t)| t).collect();
+        assert_eq!(found, ["windows path", "digits", "bytes"]);
+        let lines: Vec<usize> = extract(source).iter().map(|c| c.line).collect();
+        assert_eq!(lines, [1, 2, 3]);
+    }
+
+    #[test]
+    fn raw_strings_close_only_with_their_hashes() {
+        let source = "let s = r##\"a \"# // not a comment\"##; // comment\n";
+        assert_eq!(texts(source), [(CommentKind::Line, "comment".to_string())]);
+    }
+}
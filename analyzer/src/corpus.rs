@@ -0,0 +1,414 @@
+//! An index of every sample in the repository, in every language.
+//!
+//! Sample files are named `o1-<language>-<tier>.<ext>`, where the tier is
+//! `simple`, `complex`, `full` or `solana` (`o1-kotlin-compex.kt` carries a
+//! typo and the HTML samples have no tier at all). Rust files are split into
+//! their programs with [`split_file`]; every other sample holds a single
+//! program and is indexed whole.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::snippet::{file_stem, slugify, split_file};
+
+/// How demanding the prompt behind a sample was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Simple,
+    Complex,
+    Full,
+    Solana,
+}
+
+impl Tier {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tier::Simple => "simple",
+            Tier::Complex => "complex",
+            Tier::Full => "full",
+            Tier::Solana => "solana",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "simple" => Some(Tier::Simple),
+            "complex" | "compex" => Some(Tier::Complex),
+            "full" => Some(Tier::Full),
+            "solana" => Some(Tier::Solana),
+            _ => None,
+        }
+    }
+}
+
+/// Extensions of the languages the samples are written in. Anything else
+/// named `o1-*`, such as this tool's own `o1-index.sqlite`, is not a sample.
+pub const SOURCE_EXTENSIONS: &[&str] = &[
+    "rs", "go", "java", "kt", "cpp", "swift", "php", "rb", "py", "js", "html",
+];
+
+/// A file that follows the sample naming convention.
+#[derive(Debug, Clone)]
+pub struct SampleFile {
+    pub path: PathBuf,
+    /// The `<language>` part of the name: `rust`, `go`, `kotlin`, ...
+    pub language: String,
+    /// `None` for samples without a tier, such as `o1-html-2.html`.
+    pub tier: Option<Tier>,
+}
+
+impl SampleFile {
+    /// Parses `o1-<language>[-<tier>]` from the file name, or returns `None`
+    /// for files that are not samples, including those whose extension is
+    /// not in [`SOURCE_EXTENSIONS`].
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let extension = path.extension()?.to_str()?;
+        if !SOURCE_EXTENSIONS.contains(&extension) {
+            return None;
+        }
+        let mut parts = stem.strip_prefix("o1-")?.splitn(2, '-');
+        let language = parts.next().filter(|l| !l.is_empty())?;
+        let tier = parts.next().and_then(Tier::from_name);
+        Some(SampleFile {
+            path: path.to_path_buf(),
+            language: language.to_string(),
+            tier,
+        })
+    }
+
+    pub fn is_rust(&self) -> bool {
+        self.language == "rust"
+    }
+}
+
+/// One row of the index.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexEntry {
+    pub id: String,
+    pub source: PathBuf,
+    pub language: String,
+    pub tier: Option<Tier>,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Hex SHA-256 of the snippet's code.
+    pub sha256: String,
+}
+
+/// Finds every sample file under `root`, skipping hidden and `target`
+/// directories and the file at `output`, sorted by path.
+pub fn discover(root: &Path, output: Option<&Path>) -> Result<Vec<SampleFile>> {
+    // The output may not exist yet, and then there is nothing to skip.
+    let output = output.and_then(|path| fs::canonicalize(path).ok());
+    let mut samples = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).map_err(|e| Error::io(&dir, e))? {
+            let entry = entry.map_err(|e| Error::io(&dir, e))?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if path.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    dirs.push(path);
+                }
+            } else if let Some(sample) = SampleFile::from_path(&path) {
+                if output.is_none() || fs::canonicalize(&path).ok() != output {
+                    samples.push(sample);
+                }
+            }
+        }
+    }
+    samples.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(samples)
+}
+
+/// Splits `sample` into index entries whose `source` is relative to `root`.
+pub fn index_file(root: &Path, sample: &SampleFile) -> Result<Vec<IndexEntry>> {
+    let source = sample.path.strip_prefix(root).unwrap_or(&sample.path);
+    let entry = |id: String, start_line, end_line, code: &str| IndexEntry {
+        id,
+        source: source.to_path_buf(),
+        language: sample.language.clone(),
+        tier: sample.tier,
+        start_line,
+        end_line,
+        sha256: sha256_hex(code),
+    };
+    if sample.is_rust() {
+        return Ok(split_file(&sample.path)?
+            .iter()
+            .map(|s| entry(s.id.clone(), s.start_line, s.end_line, &s.code))
+            .collect());
+    }
+    let code = fs::read_to_string(&sample.path).map_err(|e| Error::io(&sample.path, e))?;
+    Ok(vec![entry(
+        whole_file_id(sample, &code),
+        1,
+        code.lines().count(),
+        &code,
+    )])
+}
+
+/// The snippet id of a sample indexed whole, named after its `File:
+/// Name.ext` header line when it has one.
+pub(crate) fn whole_file_id(sample: &SampleFile, code: &str) -> String {
+    format!("{}/01-{}", file_stem(&sample.path), program_name(code))
+}
+
+fn program_name(code: &str) -> String {
+    code.lines()
+        .take(10)
+        .find_map(|line| line.split_once("File:"))
+        .map(|(_, name)| name.trim())
+        .map(|name| name.rsplit_once('.').map_or(name, |(stem, _)| stem))
+        .map(slugify)
+        .unwrap_or_else(|| "main".to_string())
+}
+
+fn sha256_hex(code: &str) -> String {
+    Sha256::digest(code.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Writes `entries` to a SQLite database at `path`, replacing any previous
+/// index there.
+pub fn write_sqlite(path: &Path, entries: &[IndexEntry]) -> Result<()> {
+    let sqlite = |e| Error::sqlite(path, e);
+    let mut db = Connection::open(path).map_err(sqlite)?;
+    let tx = db.transaction().map_err(sqlite)?;
+    tx.execute_batch(
+        "DROP TABLE IF EXISTS snippets;
+         CREATE TABLE snippets (
+             id         TEXT PRIMARY KEY,
+             source     TEXT NOT NULL,
+             language   TEXT NOT NULL,
+             tier       TEXT,
+             start_line INTEGER NOT NULL,
+             end_line   INTEGER NOT NULL,
+             sha256     TEXT NOT NULL
+         );
+         CREATE INDEX snippets_language_tier ON snippets (language, tier);
+         CREATE INDEX snippets_sha256 ON snippets (sha256);",
+    )
+    .map_err(sqlite)?;
+    {
+        let mut insert = tx
+            .prepare("INSERT INTO snippets VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")
+            .map_err(sqlite)?;
+        for entry in entries {
+            insert
+                .execute(params![
+                    entry.id,
+                    entry.source.to_string_lossy(),
+                    entry.language,
+                    entry.tier.map(Tier::as_str),
+                    entry.start_line,
+                    entry.end_line,
+                    entry.sha256,
+                ])
+                .map_err(sqlite)?;
+        }
+    }
+    tx.commit().map_err(sqlite)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn repo() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+    }
+
+    /// A fresh directory for one test.
+    fn scratch(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("o1-analyzer-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn sample(name: &str) -> Option<(String, Option<Tier>)> {
+        SampleFile::from_path(Path::new(name)).map(|s| (s.language, s.tier))
+    }
+
+    #[test]
+    fn parses_sample_file_names() {
+        assert_eq!(
+            sample("o1-rust-full.rs"),
+            Some(("rust".to_string(), Some(Tier::Full)))
+        );
+        assert_eq!(
+            sample("o1-kotlin-compex.kt"),
+            Some(("kotlin".to_string(), Some(Tier::Complex)))
+        );
+        assert_eq!(sample("o1-html-2.html"), Some(("html".to_string(), None)));
+        assert_eq!(sample("o1-html.html"), Some(("html".to_string(), None)));
+        assert_eq!(sample("o1-index.sqlite"), None);
+        assert_eq!(sample("o1-dataset.jsonl"), None);
+        assert_eq!(sample("o1-rust-full"), None);
+        assert_eq!(sample("o1-.rs"), None);
+        assert_eq!(sample("README.md"), None);
+    }
+
+    #[test]
+    fn discover_walks_sorted_and_skips_non_samples() {
+        let dir = scratch("discover");
+        for name in [
+            "o1-rust-simple.rs",
+            "sub/o1-go-simple.go",
+            "sub/deeper/o1-ruby-complex.rb",
+            ".git/o1-python-simple.py",
+            "target/o1-js-simple.js",
+            "o1-notes.txt",
+            "o1-out.js",
+        ] {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "// sample\n").unwrap();
+        }
+        // A previous index is not UTF-8 and must not be read as a sample.
+        fs::write(dir.join("o1-index.sqlite"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let found = |output: Option<&Path>| -> Vec<PathBuf> {
+            discover(&dir, output)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.path.strip_prefix(&dir).unwrap().to_path_buf())
+                .collect()
+        };
+        let expected = [
+            "o1-out.js",
+            "o1-rust-simple.rs",
+            "sub/deeper/o1-ruby-complex.rb",
+            "sub/o1-go-simple.go",
+        ];
+        assert_eq!(found(None), expected.map(PathBuf::from));
+        // The output file is skipped however its path is spelled.
+        let output = dir.join("sub/../o1-out.js");
+        assert_eq!(
+            found(Some(&output)),
+            expected[1..].iter().map(PathBuf::from).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            found(Some(&dir.join("missing.js"))),
+            expected.map(PathBuf::from)
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn discover_finds_every_repository_sample() {
+        let samples = discover(&repo(), None).unwrap();
+        assert_eq!(samples.len(), 24);
+        assert!(samples[0].path.ends_with("o1-cpp-complex.cpp"));
+        assert_eq!(samples.iter().filter(|s| s.is_rust()).count(), 4);
+    }
+
+    #[test]
+    fn index_file_spans_and_hashes() {
+        let dir = scratch("index");
+        let path = dir.join("o1-go-simple.go");
+        fs::write(&path, "// File: prime_check.go\npackage main\n").unwrap();
+        let entries = index_file(&dir, &SampleFile::from_path(&path).unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.id, "o1-go-simple/01-prime-check");
+        assert_eq!(entry.source, Path::new("o1-go-simple.go"));
+        assert_eq!((entry.start_line, entry.end_line), (1, 2));
+        assert_eq!(
+            entry.sha256,
+            "20f671834a9ad3b5d8444e781a18e5e77e08ea904104b768fca9755d36354ac5"
+        );
+        let _ = fs::remove_dir_all(&dir);
+
+        let root = repo();
+        let full = SampleFile::from_path(&root.join("o1-rust-full.rs")).unwrap();
+        let entries = index_file(&root, &full).unwrap();
+        let snippets = split_file(&full.path).unwrap();
+        assert_eq!(entries.len(), 27);
+        assert_eq!(entries[0].source, Path::new("o1-rust-full.rs"));
+        assert_eq!(entries[0].start_line, 1);
+        for (entry, snippet) in entries.iter().zip(&snippets) {
+            assert_eq!(entry.id, snippet.id);
+            assert_eq!(
+                (entry.start_line, entry.end_line),
+                (snippet.start_line, snippet.end_line)
+            );
+            assert_eq!(entry.sha256, sha256_hex(&snippet.code));
+        }
+        assert!(entries.windows(2).all(|w| w[0].end_line < w[1].start_line));
+    }
+
+    #[test]
+    fn write_sqlite_round_trips_and_replaces() {
+        let dir = scratch("sqlite");
+        let db = dir.join("o1-index.sqlite");
+        let entry = |id: &str, tier| IndexEntry {
+            id: id.to_string(),
+            source: PathBuf::from("o1-rust-full.rs"),
+            language: "rust".to_string(),
+            tier,
+            start_line: 3,
+            end_line: 9,
+            sha256: "ab".repeat(32),
+        };
+        write_sqlite(&db, &[entry("old/01-main", None)]).unwrap();
+        write_sqlite(
+            &db,
+            &[
+                entry("a/01-main", Some(Tier::Full)),
+                entry("b/01-main", None),
+            ],
+        )
+        .unwrap();
+
+        let conn = Connection::open(&db).unwrap();
+        let mut query = conn
+            .prepare("SELECT id, source, language, tier, start_line, end_line, sha256 FROM snippets ORDER BY id")
+            .unwrap();
+        type Row = (String, String, String, Option<String>, usize, usize, String);
+        let rows: Vec<Row> = query
+            .query_map([], |r| {
+                Ok((
+                    r.get(0)?,
+                    r.get(1)?,
+                    r.get(2)?,
+                    r.get(3)?,
+                    r.get(4)?,
+                    r.get(5)?,
+                    r.get(6)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let row = |id: &str, tier: Option<&str>| -> Row {
+            (
+                id.to_string(),
+                "o1-rust-full.rs".to_string(),
+                "rust".to_string(),
+                tier.map(str::to_string),
+                3,
+                9,
+                "ab".repeat(32),
+            )
+        };
+        assert_eq!(
+            rows,
+            [row("a/01-main", Some("full")), row("b/01-main", None)]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,389 @@
+//! Duplicate and near-duplicate detection across snippets.
+//!
+//! Several programs appear more than once in the corpus, once under a banner
+//! and again, reworded, without one. Each snippet is reduced to the shape of
+//! its syntax tree: the sequence of node kinds (`ItemFn`, `ExprIf`,
+//! `ExprBinary(*)`, ...) with identifiers, literals and comments erased.
+//! Overlapping runs of [`SHINGLE`] kinds are counted per item, so reordering
+//! items does not hide a copy, and two snippets are compared with the
+//! weighted Jaccard similarity of those counts.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use quote::ToTokens;
+use serde::Serialize;
+use syn::visit::{self, Visit};
+use syn::{Expr, Item, Pat, Stmt, Type};
+
+use crate::snippet::Snippet;
+
+/// Number of consecutive node kinds in one shingle.
+pub const SHINGLE: usize = 4;
+
+/// The structural summary of one snippet.
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    pub id: String,
+    shingles: HashMap<u64, usize>,
+}
+
+impl Fingerprint {
+    /// Fingerprints `snippet`, or returns `None` when it does not parse.
+    pub fn new(snippet: &Snippet) -> Option<Self> {
+        let file = syn::parse_file(&snippet.code).ok()?;
+        let mut shingles = HashMap::new();
+        for item in &file.items {
+            if matches!(item, Item::Use(_) | Item::ExternCrate(_)) {
+                continue;
+            }
+            let mut kinds = KindCollector::default();
+            kinds.visit_item(item);
+            for window in shingle_windows(&kinds.kinds) {
+                let mut hasher = DefaultHasher::new();
+                window.hash(&mut hasher);
+                *shingles.entry(hasher.finish()).or_insert(0) += 1;
+            }
+        }
+        Some(Fingerprint {
+            id: snippet.id.clone(),
+            shingles,
+        })
+    }
+
+    /// Weighted Jaccard similarity in `0.0..=1.0`.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let mut intersection = 0;
+        let mut union = 0;
+        for (shingle, &count) in &self.shingles {
+            let theirs = other.shingles.get(shingle).copied().unwrap_or(0);
+            intersection += count.min(theirs);
+            union += count.max(theirs);
+        }
+        for (shingle, &count) in &other.shingles {
+            if !self.shingles.contains_key(shingle) {
+                union += count;
+            }
+        }
+        if union == 0 {
+            return 0.0;
+        }
+        intersection as f64 / union as f64
+    }
+}
+
+fn shingle_windows(kinds: &[String]) -> Vec<&[String]> {
+    if kinds.len() <= SHINGLE {
+        return vec![kinds];
+    }
+    kinds.windows(SHINGLE).collect()
+}
+
+/// Two snippets and how alike they are.
+#[derive(Debug, Clone, Serialize)]
+pub struct Pair {
+    pub a: String,
+    pub b: String,
+    pub similarity: f64,
+}
+
+/// Snippets connected by pairs at or above the clustering threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct Cluster {
+    pub members: Vec<String>,
+    /// The lowest similarity among the pairs that joined the cluster.
+    pub min_similarity: f64,
+    pub pairs: Vec<Pair>,
+}
+
+/// Groups `snippets` whose similarity reaches `threshold`.
+///
+/// Clusters are single-linkage: a snippet joins a cluster when it is similar
+/// enough to any member. Snippets that do not parse and singletons are left
+/// out.
+pub fn cluster(snippets: &[Snippet], threshold: f64) -> Vec<Cluster> {
+    let fingerprints: Vec<Fingerprint> = snippets.iter().filter_map(Fingerprint::new).collect();
+    let mut parent: Vec<usize> = (0..fingerprints.len()).collect();
+    let mut pairs = Vec::new();
+    for i in 0..fingerprints.len() {
+        for j in i + 1..fingerprints.len() {
+            let similarity = fingerprints[i].similarity(&fingerprints[j]);
+            if similarity >= threshold {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                parent[rj] = ri;
+                pairs.push((i, j, similarity));
+            }
+        }
+    }
+
+    let mut clusters: BTreeMap<usize, Cluster> = BTreeMap::new();
+    for (i, j, similarity) in pairs {
+        let root = find(&mut parent, i);
+        let cluster = clusters.entry(root).or_insert_with(|| Cluster {
+            members: Vec::new(),
+            min_similarity: 1.0,
+            pairs: Vec::new(),
+        });
+        for k in [i, j] {
+            if !cluster.members.contains(&fingerprints[k].id) {
+                cluster.members.push(fingerprints[k].id.clone());
+            }
+        }
+        cluster.min_similarity = cluster.min_similarity.min(similarity);
+        cluster.pairs.push(Pair {
+            a: fingerprints[i].id.clone(),
+            b: fingerprints[j].id.clone(),
+            similarity: (similarity * 1000.0).round() / 1000.0,
+        });
+    }
+    clusters
+        .into_values()
+        .map(|mut cluster| {
+            cluster.members.sort();
+            cluster.min_similarity = (cluster.min_similarity * 1000.0).round() / 1000.0;
+            cluster
+        })
+        .collect()
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Records node kinds in pre-order.
+#[derive(Default)]
+struct KindCollector {
+    kinds: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for KindCollector {
+    fn visit_attribute(&mut self, _: &'ast syn::Attribute) {
+        // Doc comments are attributes; comments are not structure.
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        self.kinds.push(item_kind(item).to_string());
+        visit::visit_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        let kind = match stmt {
+            Stmt::Local(_) => "StmtLocal",
+            Stmt::Item(_) => "StmtItem",
+            Stmt::Expr(_, Some(_)) => "StmtSemi",
+            Stmt::Expr(_, None) => "StmtExpr",
+            Stmt::Macro(_) => "StmtMacro",
+        };
+        self.kinds.push(kind.to_string());
+        visit::visit_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        let kind = match expr {
+            Expr::Binary(b) => format!("ExprBinary({})", b.op.to_token_stream()),
+            Expr::Unary(u) => format!("ExprUnary({})", u.op.to_token_stream()),
+            Expr::Lit(l) => match &l.lit {
+                syn::Lit::Str(_) => "LitStr".to_string(),
+                syn::Lit::Int(_) | syn::Lit::Float(_) => "LitNumber".to_string(),
+                _ => "Lit".to_string(),
+            },
+            other => expr_kind(other).to_string(),
+        };
+        self.kinds.push(kind);
+        visit::visit_expr(self, expr);
+    }
+
+    fn visit_pat(&mut self, pat: &'ast Pat) {
+        self.kinds.push(pat_kind(pat).to_string());
+        visit::visit_pat(self, pat);
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        self.kinds.push(type_kind(ty).to_string());
+        visit::visit_type(self, ty);
+    }
+}
+
+fn item_kind(item: &Item) -> &'static str {
+    match item {
+        Item::Const(_) => "ItemConst",
+        Item::Enum(_) => "ItemEnum",
+        Item::ExternCrate(_) => "ItemExternCrate",
+        Item::Fn(_) => "ItemFn",
+        Item::ForeignMod(_) => "ItemForeignMod",
+        Item::Impl(i) if i.trait_.is_some() => "ItemImplTrait",
+        Item::Impl(_) => "ItemImpl",
+        Item::Macro(_) => "ItemMacro",
+        Item::Mod(_) => "ItemMod",
+        Item::Static(_) => "ItemStatic",
+        Item::Struct(_) => "ItemStruct",
+        Item::Trait(_) => "ItemTrait",
+        Item::TraitAlias(_) => "ItemTraitAlias",
+        Item::Type(_) => "ItemType",
+        Item::Union(_) => "ItemUnion",
+        Item::Use(_) => "ItemUse",
+        _ => "Item",
+    }
+}
+
+fn expr_kind(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Array(_) => "ExprArray",
+        Expr::Assign(_) => "ExprAssign",
+        Expr::Async(_) => "ExprAsync",
+        Expr::Await(_) => "ExprAwait",
+        Expr::Block(_) => "ExprBlock",
+        Expr::Break(_) => "ExprBreak",
+        Expr::Call(_) => "ExprCall",
+        Expr::Cast(_) => "ExprCast",
+        Expr::Closure(_) => "ExprClosure",
+        Expr::Const(_) => "ExprConst",
+        Expr::Continue(_) => "ExprContinue",
+        Expr::Field(_) => "ExprField",
+        Expr::ForLoop(_) => "ExprForLoop",
+        Expr::Group(_) => "ExprGroup",
+        Expr::If(_) => "ExprIf",
+        Expr::Index(_) => "ExprIndex",
+        Expr::Infer(_) => "ExprInfer",
+        Expr::Let(_) => "ExprLet",
+        Expr::Loop(_) => "ExprLoop",
+        Expr::Macro(_) => "ExprMacro",
+        Expr::Match(_) => "ExprMatch",
+        Expr::MethodCall(_) => "ExprMethodCall",
+        Expr::Paren(_) => "ExprParen",
+        Expr::Path(_) => "ExprPath",
+        Expr::Range(_) => "ExprRange",
+        Expr::Reference(_) => "ExprReference",
+        Expr::Repeat(_) => "ExprRepeat",
+        Expr::Return(_) => "ExprReturn",
+        Expr::Struct(_) => "ExprStruct",
+        Expr::Try(_) => "ExprTry",
+        Expr::TryBlock(_) => "ExprTryBlock",
+        Expr::Tuple(_) => "ExprTuple",
+        Expr::Unsafe(_) => "ExprUnsafe",
+        Expr::While(_) => "ExprWhile",
+        Expr::Yield(_) => "ExprYield",
+        _ => "Expr",
+    }
+}
+
+fn pat_kind(pat: &Pat) -> &'static str {
+    match pat {
+        Pat::Ident(_) => "PatIdent",
+        Pat::Lit(_) => "PatLit",
+        Pat::Macro(_) => "PatMacro",
+        Pat::Or(_) => "PatOr",
+        Pat::Paren(_) => "PatParen",
+        Pat::Path(_) => "PatPath",
+        Pat::Range(_) => "PatRange",
+        Pat::Reference(_) => "PatReference",
+        Pat::Rest(_) => "PatRest",
+        Pat::Slice(_) => "PatSlice",
+        Pat::Struct(_) => "PatStruct",
+        Pat::Tuple(_) => "PatTuple",
+        Pat::TupleStruct(_) => "PatTupleStruct",
+        Pat::Type(_) => "PatType",
+        Pat::Wild(_) => "PatWild",
+        _ => "Pat",
+    }
+}
+
+fn type_kind(ty: &Type) -> &'static str {
+    match ty {
+        Type::Array(_) => "TypeArray",
+        Type::BareFn(_) => "TypeBareFn",
+        Type::ImplTrait(_) => "TypeImplTrait",
+        Type::Infer(_) => "TypeInfer",
+        Type::Macro(_) => "TypeMacro",
+        Type::Never(_) => "TypeNever",
+        Type::Paren(_) => "TypeParen",
+        Type::Path(_) => "TypePath",
+        Type::Ptr(_) => "TypePtr",
+        Type::Reference(_) => "TypeReference",
+        Type::Slice(_) => "TypeSlice",
+        Type::TraitObject(_) => "TypeTraitObject",
+        Type::Tuple(_) => "TypeTuple",
+        _ => "Type",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::{split_file, split_source};
+
+    fn fingerprint(source: &str) -> Fingerprint {
+        let snippets = split_source(Path::new("fixture.rs"), source).unwrap();
+        Fingerprint::new(&snippets[0]).unwrap()
+    }
+
+    #[test]
+    fn renaming_and_comments_do_not_change_the_shape() {
+        let a = fingerprint(
+            "fn largest(v: &[i32]) -> i32 {\n    let mut m = v[0];\n    \
+             for &x in v { if x > m { m = x; } }\n    m\n}\n",
+        );
+        let b = fingerprint(
+            "/// Finds the maximum.\nfn max_of(items: &[i32]) -> i32 {\n    \
+             // Start from the first one.\n    let mut best = items[0];\n    \
+             for &item in items { if item > best { best = item; } }\n    best\n}\n",
+        );
+        assert_eq!(a.similarity(&b), 1.0);
+    }
+
+    #[test]
+    fn different_programs_are_not_alike() {
+        let a = fingerprint("fn main() { let v = vec![1, 2, 3]; println!(\"{:?}\", v); }\n");
+        let b = fingerprint(
+            "struct Point { x: f64, y: f64 }\n\
+             impl Point { fn norm(&self) -> f64 { (self.x * self.x + self.y * self.y).sqrt() } }\n",
+        );
+        assert!(a.similarity(&b) < 0.1, "{}", a.similarity(&b));
+    }
+
+    #[test]
+    fn sample_copies_cluster_together() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-full.rs");
+        let snippets = split_file(&path).unwrap();
+        let clusters = cluster(&snippets, 0.35);
+        let members: Vec<Vec<&str>> = clusters
+            .iter()
+            .map(|c| c.members.iter().map(String::as_str).collect())
+            .collect();
+        assert_eq!(
+            members,
+            [
+                [
+                    "o1-rust-full/01-factorial-example-simple",
+                    "o1-rust-full/17-factorial"
+                ],
+                [
+                    "o1-rust-full/02-finding-the-largest-element-in-a-vector",
+                    "o1-rust-full/18-find-largest"
+                ],
+                [
+                    "o1-rust-full/07-web-server-using-actix-hello-world",
+                    "o1-rust-full/24-helloresponse"
+                ],
+                [
+                    "o1-rust-full/08-csv-parsing-and-processing-example",
+                    "o1-rust-full/25-main"
+                ],
+                [
+                    "o1-rust-full/11-quicksort-algorithm-in-place",
+                    "o1-rust-full/20-quicksort"
+                ],
+            ]
+        );
+        assert_eq!(clusters[1].min_similarity, 1.0);
+        // A stricter threshold keeps only the closest copies.
+        assert_eq!(cluster(&snippets, 0.9).len(), 1);
+    }
+}
@@ -0,0 +1,518 @@
+//! Dependency resolution for extracted snippets.
+//!
+//! Banners declare dependencies inconsistently: some pin versions in a
+//! `[dependencies]` block, some only mention crate names, and unbannered
+//! snippets declare nothing. The code is the ground truth, so the crates a
+//! snippet actually names (in `use` items, `extern crate`, attributes such as
+//! `#[tokio::main]`, and absolute paths like `spl_token::id()`) are merged
+//! with the banner declarations and pinned against [`PINS`]. Crates that
+//! neither pins nor the banner give a version for are reported as
+//! unresolved instead of being left to float, and crates another snippet of
+//! the same file defines are left out.
+
+use std::collections::HashSet;
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use syn::visit::{self, Visit};
+use syn::{Item, UseTree};
+
+use crate::metadata::{self, same_crate};
+use crate::snippet::Snippet;
+
+/// Pinned `(package, version)` for every third-party crate the corpus uses.
+pub const PINS: &[(&str, &str)] = &[
+    ("actix-web", "4.9.0"),
+    ("anchor-lang", "0.30.1"),
+    ("anchor-spl", "0.30.1"),
+    ("clap", "4.5.20"),
+    ("csv", "1.3.0"),
+    ("ndarray", "0.16.1"),
+    ("reqwest", "0.11.27"),
+    ("rocket", "0.5.1"),
+    ("serde", "1.0.210"),
+    ("serde_json", "1.0.128"),
+    ("solana-client", "1.18.26"),
+    ("solana-program", "1.18.26"),
+    ("solana-program-test", "1.18.26"),
+    ("solana-sdk", "1.18.26"),
+    ("spl-token", "4.0.0"),
+    ("tokio", "1.40.0"),
+];
+
+/// Features implied by a path the code uses: `(path prefix, package, feature)`.
+const USAGE_FEATURES: &[(&str, &str, &str)] = &[
+    ("clap::Parser", "clap", "derive"),
+    ("reqwest::blocking", "reqwest", "blocking"),
+    ("rocket::serde::json", "rocket", "json"),
+    ("serde::Deserialize", "serde", "derive"),
+    ("serde::Serialize", "serde", "derive"),
+    ("tokio::main", "tokio", "macros"),
+    ("tokio::main", "tokio", "rt-multi-thread"),
+    ("tokio::test", "tokio", "macros"),
+    ("tokio::test", "tokio", "rt"),
+];
+
+/// Path roots that never name a dependency.
+pub(crate) const BUILTIN_ROOTS: &[&str] = &[
+    "std", "core", "alloc", "crate", "self", "super", "Self", "bool", "char", "str", "f32", "f64",
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+/// Where a resolved dependency's version came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionSource {
+    /// The pinned version, compatible with any banner requirement.
+    Pinned,
+    /// The banner's requirement, which the pinned version does not satisfy.
+    Banner,
+}
+
+/// One `[dependencies]` entry of a generated manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedDependency {
+    pub package: String,
+    /// A Cargo version requirement, `=x.y.z` when pinned.
+    pub version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    pub source: VersionSource,
+}
+
+/// A disagreement between a banner and the code it describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The banner lists the crate but the code never names it.
+    DeclaredNotUsed(String),
+    /// The code names the crate but the banner does not list it.
+    UsedNotDeclared(String),
+    /// The code needs a feature the banner's declaration leaves out.
+    MissingFeature { package: String, feature: String },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::DeclaredNotUsed(name) => {
+                write!(f, "`{}` is declared in the banner but never used", name)
+            }
+            Mismatch::UsedNotDeclared(name) => {
+                write!(f, "`{}` is used but not declared in the banner", name)
+            }
+            Mismatch::MissingFeature { package, feature } => write!(
+                f,
+                "`{}` is used with feature `{}` the banner does not enable",
+                package, feature
+            ),
+        }
+    }
+}
+
+/// Dependencies for one snippet plus everything the banner got wrong.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    pub dependencies: Vec<ResolvedDependency>,
+    pub mismatches: Vec<Mismatch>,
+    /// Crates the code names that have no pin and no banner version. They
+    /// are left out of `dependencies`, so the package will not build until
+    /// they are pinned.
+    pub unresolved: Vec<String>,
+    /// Crates the code names that another snippet of the same file defines
+    /// (see [`Snippet::sibling_crates`]); left out of `dependencies`.
+    pub local: Vec<String>,
+}
+
+/// Resolves the dependencies of `snippet` from its banner and its code.
+pub fn resolve(snippet: &Snippet) -> Resolution {
+    let declared = snippet
+        .banner
+        .as_ref()
+        .map(|b| metadata::parse(b).dependencies)
+        .unwrap_or_default();
+    let paths = match syn::parse_file(&snippet.code) {
+        Ok(file) => used_paths(&file),
+        Err(_) => Vec::new(),
+    };
+
+    let mut used: Vec<String> = Vec::new();
+    for path in &paths {
+        let root = path.split("::").next().unwrap_or_default();
+        let package = package_name(root);
+        if !used.iter().any(|u| same_crate(u, &package)) {
+            used.push(package);
+        }
+    }
+
+    let mut resolution = Resolution::default();
+    // A banner that names no crates at all makes no claim to contradict.
+    let claims = !declared.is_empty();
+    for dependency in &declared {
+        if !used.iter().any(|u| same_crate(u, &dependency.name)) {
+            resolution
+                .mismatches
+                .push(Mismatch::DeclaredNotUsed(dependency.name.clone()));
+        }
+    }
+    for package in &used {
+        if snippet
+            .sibling_crates
+            .iter()
+            .any(|c| same_crate(c, package))
+        {
+            resolution.local.push(package.clone());
+            continue;
+        }
+        let declaration = declared.iter().find(|d| same_crate(&d.name, package));
+        if claims && declaration.is_none() {
+            resolution
+                .mismatches
+                .push(Mismatch::UsedNotDeclared(package.clone()));
+        }
+
+        let mut features: Vec<String> = Vec::new();
+        let pin = PINS.iter().find(|(name, _)| same_crate(name, package));
+        if let Some(declaration) = declaration {
+            features.extend(declaration.features.iter().cloned());
+        }
+        for (prefix, feature_package, feature) in USAGE_FEATURES {
+            let needed =
+                same_crate(feature_package, package) && paths.iter().any(|p| p.starts_with(prefix));
+            if !needed {
+                continue;
+            }
+            if let Some(declaration) = declaration {
+                if declaration.version.is_some()
+                    && !declaration.features.iter().any(|f| f == feature)
+                {
+                    resolution.mismatches.push(Mismatch::MissingFeature {
+                        package: package.clone(),
+                        feature: feature.to_string(),
+                    });
+                }
+            }
+            features.push(feature.to_string());
+        }
+        features.sort();
+        features.dedup();
+
+        let requirement = declaration.and_then(|d| d.version.as_deref());
+        let (version, source) = match (pin, requirement) {
+            (Some((_, pinned)), Some(req)) if !pin_satisfies(pinned, req) => {
+                (req.to_string(), VersionSource::Banner)
+            }
+            (Some((_, pinned)), _) => (format!("={}", pinned), VersionSource::Pinned),
+            (None, Some(req)) => (req.to_string(), VersionSource::Banner),
+            (None, None) => {
+                resolution.unresolved.push(package.clone());
+                continue;
+            }
+        };
+        resolution.dependencies.push(ResolvedDependency {
+            package: package.clone(),
+            version,
+            features,
+            source,
+        });
+    }
+    resolution
+        .dependencies
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    resolution
+}
+
+/// Renders the `[dependencies]` table body for `dependencies`.
+pub fn render(dependencies: &[ResolvedDependency]) -> String {
+    let mut out = String::new();
+    for dependency in dependencies {
+        if dependency.features.is_empty() {
+            out.push_str(&format!(
+                "{} = \"{}\"\n",
+                dependency.package, dependency.version
+            ));
+        } else {
+            let features: Vec<String> = dependency
+                .features
+                .iter()
+                .map(|f| format!("\"{}\"", f))
+                .collect();
+            out.push_str(&format!(
+                "{} = {{ version = \"{}\", features = [{}] }}\n",
+                dependency.package,
+                dependency.version,
+                features.join(", ")
+            ));
+        }
+    }
+    out
+}
+
+fn pin_satisfies(pinned: &str, requirement: &str) -> bool {
+    match (Version::parse(pinned), VersionReq::parse(requirement)) {
+        (Ok(version), Ok(req)) => req.matches(&version),
+        _ => false,
+    }
+}
+
+/// Maps a crate root as written in code (`actix_web`) to its package name.
+fn package_name(root: &str) -> String {
+    PINS.iter()
+        .find(|(name, _)| same_crate(name, root))
+        .map_or_else(|| root.to_string(), |(name, _)| name.to_string())
+}
+
+/// Every external path the file names, fully expanded, e.g.
+/// `solana_sdk::signature::Keypair` or `tokio::main`.
+fn used_paths(file: &syn::File) -> Vec<String> {
+    let mut collector = PathCollector::default();
+    collector.collect_local_names(&file.items);
+    collector.visit_file(file);
+    collector.paths
+}
+
+#[derive(Default)]
+struct PathCollector {
+    /// Names bound by local items or `use` imports, which shadow crate roots.
+    local: HashSet<String>,
+    paths: Vec<String>,
+}
+
+impl PathCollector {
+    fn collect_local_names(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Mod(m) => {
+                    self.local.insert(m.ident.to_string());
+                    if let Some((_, items)) = &m.content {
+                        self.collect_local_names(items);
+                    }
+                }
+                Item::Fn(f) => {
+                    self.local.insert(f.sig.ident.to_string());
+                }
+                Item::Struct(s) => {
+                    self.local.insert(s.ident.to_string());
+                }
+                Item::Enum(e) => {
+                    self.local.insert(e.ident.to_string());
+                }
+                Item::Trait(t) => {
+                    self.local.insert(t.ident.to_string());
+                }
+                Item::Use(u) => bound_names(&u.tree, &mut self.local),
+                _ => {}
+            }
+        }
+    }
+
+    fn push(&mut self, path: String) {
+        let root = path.split("::").next().unwrap_or_default();
+        if BUILTIN_ROOTS.contains(&root) || !self.paths.iter().all(|p| p != &path) {
+            return;
+        }
+        self.paths.push(path);
+    }
+}
+
+impl<'ast> Visit<'ast> for PathCollector {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut expanded = Vec::new();
+        expand_use(&item.tree, String::new(), &mut expanded);
+        for path in expanded {
+            self.push(path);
+        }
+    }
+
+    fn visit_item_extern_crate(&mut self, item: &'ast syn::ItemExternCrate) {
+        self.push(item.ident.to_string());
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.segments.len() > 1 {
+            let root = path.segments[0].ident.to_string();
+            if !self.local.contains(&root) && root.starts_with(|c: char| c.is_ascii_lowercase()) {
+                let joined: Vec<String> =
+                    path.segments.iter().map(|s| s.ident.to_string()).collect();
+                self.push(joined.join("::"));
+            }
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+/// Expands a use tree into one full path per imported name.
+fn expand_use(tree: &UseTree, prefix: String, out: &mut Vec<String>) {
+    let join = |name: String| {
+        if prefix.is_empty() {
+            name
+        } else {
+            format!("{}::{}", prefix, name)
+        }
+    };
+    match tree {
+        UseTree::Path(p) => expand_use(&p.tree, join(p.ident.to_string()), out),
+        UseTree::Name(n) if n.ident == "self" => out.push(prefix),
+        UseTree::Name(n) => out.push(join(n.ident.to_string())),
+        UseTree::Rename(r) => out.push(join(r.ident.to_string())),
+        UseTree::Glob(_) => out.push(join("*".to_string())),
+        UseTree::Group(g) => {
+            for tree in &g.items {
+                expand_use(tree, prefix.clone(), out);
+            }
+        }
+    }
+}
+
+/// Names a use tree brings into scope (`token` for `anchor_spl::token::{self}`).
+fn bound_names(tree: &UseTree, out: &mut HashSet<String>) {
+    bound_names_under(tree, None, out);
+}
+
+fn bound_names_under(tree: &UseTree, parent: Option<&syn::Ident>, out: &mut HashSet<String>) {
+    match tree {
+        UseTree::Path(p) => bound_names_under(&p.tree, Some(&p.ident), out),
+        UseTree::Name(n) if n.ident == "self" => {
+            if let Some(parent) = parent {
+                out.insert(parent.to_string());
+            }
+        }
+        UseTree::Name(n) => {
+            out.insert(n.ident.to_string());
+        }
+        UseTree::Rename(r) => {
+            out.insert(r.rename.to_string());
+        }
+        UseTree::Glob(_) => {}
+        UseTree::Group(g) => {
+            for tree in &g.items {
+                bound_names_under(tree, parent, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::{split_file, split_source};
+
+    fn resolve_one(source: &str) -> Resolution {
+        let snippets = split_source(Path::new("fixture.rs"), source).unwrap();
+        assert_eq!(snippets.len(), 1);
+        resolve(&snippets[0])
+    }
+
+    fn find<'a>(resolution: &'a Resolution, package: &str) -> &'a ResolvedDependency {
+        resolution
+            .dependencies
+            .iter()
+            .find(|d| d.package == package)
+            .unwrap()
+    }
+
+    #[test]
+    fn usage_pins_crates_and_enables_features() {
+        let resolution = resolve_one(
+            "use serde::Serialize;\n\
+             #[derive(Serialize)]\nstruct Point { x: i32 }\n\
+             #[tokio::main]\nasync fn main() { let _ = serde_json::to_string(&Point { x: 1 }); }\n",
+        );
+        let packages: Vec<&str> = resolution
+            .dependencies
+            .iter()
+            .map(|d| d.package.as_str())
+            .collect();
+        assert_eq!(packages, ["serde", "serde_json", "tokio"]);
+        let tokio = find(&resolution, "tokio");
+        assert_eq!(tokio.version, "=1.40.0");
+        assert_eq!(tokio.features, ["macros", "rt-multi-thread"]);
+        assert_eq!(tokio.source, VersionSource::Pinned);
+        assert_eq!(find(&resolution, "serde").features, ["derive"]);
+        // No banner, so nothing to disagree with.
+        assert!(resolution.mismatches.is_empty());
+    }
+
+    #[test]
+    fn banner_disagreements_are_reported() {
+        let resolution = resolve_one(
+            "/*\n=====\nDemo\n=====\nCrates/Dependencies:\n    [dependencies]\n    \
+             tokio = \"1\"\n    rand = \"0.8\"\n*/\n\
+             use clap::Parser;\n#[tokio::main]\nasync fn main() {}\n",
+        );
+        assert_eq!(
+            resolution.mismatches,
+            [
+                Mismatch::DeclaredNotUsed("rand".to_string()),
+                Mismatch::UsedNotDeclared("clap".to_string()),
+                Mismatch::MissingFeature {
+                    package: "tokio".to_string(),
+                    feature: "macros".to_string()
+                },
+                Mismatch::MissingFeature {
+                    package: "tokio".to_string(),
+                    feature: "rt-multi-thread".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn banner_versions_override_incompatible_pins() {
+        let resolution = resolve_one(
+            "/*\n=====\nDemo\n=====\nCrates/Dependencies:\n    [dependencies]\n    \
+             reqwest = \"0.12\"\n    serde = \"1\"\n*/\n\
+             fn main() { let _ = reqwest::get; let _ = serde::de::IgnoredAny; }\n",
+        );
+        let reqwest = find(&resolution, "reqwest");
+        assert_eq!(
+            (reqwest.version.as_str(), reqwest.source),
+            ("0.12", VersionSource::Banner)
+        );
+        let serde = find(&resolution, "serde");
+        assert_eq!(
+            (serde.version.as_str(), serde.source),
+            ("=1.0.210", VersionSource::Pinned)
+        );
+    }
+
+    #[test]
+    fn unpinned_crates_are_unresolved() {
+        let resolution = resolve_one("fn main() { let _ = rand::random::<u8>(); }\n");
+        assert!(resolution.dependencies.is_empty());
+        assert_eq!(resolution.unresolved, ["rand"]);
+    }
+
+    #[test]
+    fn sibling_programs_are_local() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-solana.rs");
+        let snippets = split_file(&path).unwrap();
+        let resolution = resolve(&snippets[11]);
+        assert_eq!(resolution.local, ["counter"]);
+        assert!(resolution.unresolved.is_empty());
+        assert_eq!(find(&resolution, "tokio").features, ["macros", "rt"]);
+    }
+
+    #[test]
+    fn render_writes_features_inline() {
+        let dependencies = [
+            ResolvedDependency {
+                package: "csv".to_string(),
+                version: "=1.3.0".to_string(),
+                features: Vec::new(),
+                source: VersionSource::Pinned,
+            },
+            ResolvedDependency {
+                package: "tokio".to_string(),
+                version: "=1.40.0".to_string(),
+                features: vec!["macros".to_string(), "rt".to_string()],
+                source: VersionSource::Pinned,
+            },
+        ];
+        assert_eq!(
+            render(&dependencies),
+            "csv = \"=1.3.0\"\ntokio = { version = \"=1.40.0\", features = [\"macros\", \"rt\"] }\n"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Writing snippets out as standalone Cargo packages.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::deps;
+use crate::error::{Error, Result};
+use crate::snippet::{Snippet, SnippetKind};
+
+/// Writes `snippet` as a package under `<root>/<file stem>/<index>-<slug>/`
+/// and returns the package directory.
+///
+/// Binaries become `src/main.rs`, libraries `src/lib.rs`, and test-only
+/// snippets `tests/<slug>.rs` next to an empty library target.
+pub fn write_package(root: &Path, snippet: &Snippet) -> Result<PathBuf> {
+    let dir = root.join(snippet.source_stem()).join(snippet.dir_name());
+    match snippet.kind {
+        SnippetKind::Binary => write(&dir.join("src/main.rs"), &snippet.code)?,
+        SnippetKind::Library => write(&dir.join("src/lib.rs"), &snippet.code)?,
+        SnippetKind::Test => {
+            write(&dir.join("src/lib.rs"), "")?;
+            let test = format!("tests/{}.rs", snippet.slug.replace('-', "_"));
+            write(&dir.join(test), &snippet.code)?;
+        }
+    }
+    write(&dir.join("Cargo.toml"), &manifest(snippet))?;
+    Ok(dir)
+}
+
+/// Renders the `Cargo.toml` for `snippet`, with dependencies pinned by
+/// [`deps::resolve`].
+///
+/// The empty `[workspace]` table keeps every package independent even when
+/// the output directory sits inside another workspace.
+pub fn manifest(snippet: &Snippet) -> String {
+    let dependencies = deps::resolve(snippet).dependencies;
+    format!(
+        "[package]\n\
+         name = \"{}\"\n\
+         version = \"0.1.0\"\n\
+         edition = \"2021\"\n\
+         publish = false\n\
+         \n\
+         [dependencies]\n\
+         {}\
+         \n\
+         [workspace]\n",
+        snippet.package_name(),
+        deps::render(&dependencies)
+    )
+}
+
+fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    fs::write(path, contents).map_err(|e| Error::io(path, e))
+}
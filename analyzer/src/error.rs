@@ -0,0 +1,65 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors produced while reading, splitting, emitting or indexing samples.
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// A Rust sample is not syntactically valid.
+    Parse { path: PathBuf, source: syn::Error },
+    /// An index database could not be written.
+    Sqlite {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn sqlite(path: impl Into<PathBuf>, source: rusqlite::Error) -> Self {
+        Error::Sqlite {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse { path, source } => {
+                let start = source.span().start();
+                write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    path.display(),
+                    start.line,
+                    start.column + 1,
+                    source
+                )
+            }
+            Error::Sqlite { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            Error::Sqlite { source, .. } => Some(source),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,125 @@
+//! Instruction/response records for fine-tuning and detection jobs.
+//!
+//! Each snippet becomes one record pairing the prompt it most likely answered
+//! with the code that came back. The prompt is inferred from the banner's
+//! `Purpose` section, falling back to the banner title; snippets without a
+//! banner, and the single-program samples in other languages, have none.
+
+use std::fs;
+
+use serde::Serialize;
+
+use crate::corpus::{self, SampleFile, Tier};
+use crate::deps::{self, ResolvedDependency};
+use crate::error::{Error, Result};
+use crate::metadata;
+use crate::snippet::split_file;
+use crate::verify::{self, Status};
+
+/// One exported record.
+#[derive(Debug, Clone, Serialize)]
+pub struct Record {
+    pub id: String,
+    pub prompt: Option<String>,
+    /// The snippet's code.
+    pub response: String,
+    pub language: String,
+    pub tier: Option<Tier>,
+    /// Resolved crates for Rust snippets; empty for other languages.
+    pub dependencies: Vec<ResolvedDependency>,
+    /// `None` unless verification was requested and the snippet is Rust.
+    pub verification: Option<Status>,
+}
+
+/// Builds the records for `sample`, verifying Rust snippets when `verify`
+/// is given.
+pub fn records(sample: &SampleFile, verify: Option<&verify::Options>) -> Result<Vec<Record>> {
+    if !sample.is_rust() {
+        let code = fs::read_to_string(&sample.path).map_err(|e| Error::io(&sample.path, e))?;
+        return Ok(vec![Record {
+            id: corpus::whole_file_id(sample, &code),
+            prompt: None,
+            response: code,
+            language: sample.language.clone(),
+            tier: sample.tier,
+            dependencies: Vec::new(),
+            verification: None,
+        }]);
+    }
+
+    let mut records = Vec::new();
+    for snippet in split_file(&sample.path)? {
+        let prompt = snippet.banner.as_ref().map(|banner| {
+            metadata::parse(banner)
+                .purpose
+                .unwrap_or_else(|| banner.title.clone())
+        });
+        let verification = match verify {
+            Some(options) => Some(verify::verify(&snippet, options)?.status),
+            None => None,
+        };
+        records.push(Record {
+            id: snippet.id.clone(),
+            prompt,
+            dependencies: deps::resolve(&snippet).dependencies,
+            response: snippet.code,
+            language: sample.language.clone(),
+            tier: sample.tier,
+            verification,
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn sample(name: &str) -> SampleFile {
+        SampleFile::from_path(&Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(name)).unwrap()
+    }
+
+    #[test]
+    fn rust_samples_give_one_record_per_snippet() {
+        let records = records(&sample("o1-rust-full.rs"), None).unwrap();
+        assert_eq!(records.len(), 27);
+
+        let first = &records[0];
+        assert_eq!(first.id, "o1-rust-full/01-factorial-example-simple");
+        assert_eq!(
+            first.prompt.as_deref(),
+            Some("Demonstrate a basic recursive factorial function in Rust.")
+        );
+        assert!(first.response.contains("fn factorial"));
+        assert_eq!(first.language, "rust");
+        assert_eq!(first.tier, Some(Tier::Full));
+        assert!(first.dependencies.is_empty());
+        assert_eq!(first.verification, None);
+
+        let packages: Vec<&str> = records[6]
+            .dependencies
+            .iter()
+            .map(|d| d.package.as_str())
+            .collect();
+        assert_eq!(packages, ["actix-web"]);
+        // Unbannered snippets have no prompt to recover.
+        assert!(records[16..].iter().all(|r| r.prompt.is_none()));
+    }
+
+    #[test]
+    fn other_languages_give_one_whole_file_record() {
+        let go = records(&sample("o1-go-simple.go"), None).unwrap();
+        assert_eq!(go.len(), 1);
+        assert_eq!(go[0].id, "o1-go-simple/01-prime-check");
+        assert_eq!(go[0].language, "go");
+        assert_eq!(go[0].tier, Some(Tier::Simple));
+        assert!(go[0].prompt.is_none());
+        assert!(go[0].response.starts_with("// File: prime_check.go"));
+
+        let html = records(&sample("o1-html-2.html"), None).unwrap();
+        assert_eq!(html[0].id, "o1-html-2/01-main");
+        assert_eq!(html[0].tier, None);
+    }
+}
@@ -0,0 +1,658 @@
+//! Static checks for APIs the model made up.
+//!
+//! Every crate path a snippet names is resolved against the rustdoc JSON of
+//! the crate versions it is pinned to (see [`crate::rustdoc`]), following its
+//! own `use` imports and glob imports. On top of that:
+//!
+//! - associated functions that come from a trait (`Pubkey::from_str`) need
+//!   the trait in scope;
+//! - string literals used as pubkeys (`declare_id!`, `pubkey!`,
+//!   `Pubkey::from_str`) must be base58 for exactly 32 bytes;
+//! - a sibling snippet's Anchor program is treated as a crate, so client
+//!   code that calls its on-chain instruction handlers directly is caught.
+//!
+//! Paths into crates whose docs are unavailable are counted but not judged.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use syn::visit::{self, Visit};
+use syn::{Expr, Item, Lit, UseTree};
+
+use crate::deps::BUILTIN_ROOTS;
+use crate::rustdoc::{Docs, Lookup};
+use crate::snippet::Snippet;
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Names `declare_id!` and `#[program]` add to an Anchor program crate.
+const ANCHOR_GENERATED: &[&str] = &[
+    "ID",
+    "ID_CONST",
+    "accounts",
+    "check_id",
+    "cpi",
+    "entry",
+    "entrypoint",
+    "id",
+    "id_const",
+    "instruction",
+    "program",
+];
+
+/// Traits in the std prelude, which never need importing.
+const PRELUDE_TRAITS: &[&str] = &[
+    "AsMut",
+    "AsRef",
+    "Clone",
+    "Default",
+    "DoubleEndedIterator",
+    "Drop",
+    "ExactSizeIterator",
+    "Extend",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "FromIterator",
+    "Into",
+    "IntoIterator",
+    "Iterator",
+    "Ord",
+    "PartialEq",
+    "PartialOrd",
+    "ToOwned",
+    "ToString",
+    "TryFrom",
+    "TryInto",
+];
+
+/// One made-up or misused API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Finding {
+    /// A segment of the path does not exist.
+    MissingItem { path: String, segment: String },
+    /// An associated function provided by a trait that is not imported.
+    TraitNotInScope { path: String, trait_name: String },
+    /// A pubkey literal that is not base58 for 32 bytes.
+    InvalidPubkey { literal: String, reason: String },
+    /// Client code calling an on-chain instruction handler of a program.
+    HandlerAsClient { path: String, program: String },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::MissingItem { path, segment } => {
+                write!(f, "`{}` does not exist (no `{}`)", path, segment)
+            }
+            Finding::TraitNotInScope { path, trait_name } => write!(
+                f,
+                "`{}` needs trait `{}` in scope, which is not imported",
+                path, trait_name
+            ),
+            Finding::InvalidPubkey { literal, reason } => {
+                write!(f, "pubkey \"{}\" is invalid: {}", literal, reason)
+            }
+            Finding::HandlerAsClient { path, program } => write!(
+                f,
+                "`{}` calls an instruction handler of program `{}` as a client function",
+                path, program
+            ),
+        }
+    }
+}
+
+/// The check result for one snippet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub id: String,
+    /// Paths and pubkey literals that could be judged.
+    pub checked: usize,
+    /// Paths into crates without docs.
+    pub unchecked: usize,
+    pub findings: Vec<Finding>,
+    /// Findings per checked path or literal.
+    pub hallucination_rate: f64,
+}
+
+/// An Anchor program defined by a snippet, addressable from its siblings as
+/// a crate of the same name.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub name: String,
+    /// Names at the crate root: the snippet's top-level items plus what
+    /// Anchor generates.
+    pub exports: HashSet<String>,
+    /// Instruction handlers declared inside the `#[program]` module.
+    pub handlers: HashSet<String>,
+}
+
+/// Finds the `#[program]` modules among `snippets`.
+pub fn programs(snippets: &[Snippet]) -> Vec<Program> {
+    let mut programs = Vec::new();
+    for snippet in snippets {
+        let Ok(file) = syn::parse_file(&snippet.code) else {
+            continue;
+        };
+        let exports: HashSet<String> = file
+            .items
+            .iter()
+            .filter_map(item_name)
+            .chain(ANCHOR_GENERATED.iter().map(|s| s.to_string()))
+            .collect();
+        for item in &file.items {
+            let Item::Mod(module) = item else {
+                continue;
+            };
+            if !module.attrs.iter().any(|a| a.path().is_ident("program")) {
+                continue;
+            }
+            let handlers = module
+                .content
+                .iter()
+                .flat_map(|(_, items)| items)
+                .filter_map(|item| match item {
+                    Item::Fn(f) => Some(f.sig.ident.to_string()),
+                    _ => None,
+                })
+                .collect();
+            programs.push(Program {
+                name: module.ident.to_string(),
+                exports: exports.clone(),
+                handlers,
+            });
+        }
+    }
+    programs
+}
+
+/// Checks `snippet`, resolving paths through `docs` and through the
+/// `programs` its siblings define.
+pub fn check(snippet: &Snippet, docs: &mut Docs, programs: &[Program]) -> Report {
+    let mut report = Report {
+        id: snippet.id.clone(),
+        checked: 0,
+        unchecked: 0,
+        findings: Vec::new(),
+        hallucination_rate: 0.0,
+    };
+    let Ok(file) = syn::parse_file(&snippet.code) else {
+        return report;
+    };
+    let mut scope = Scope::default();
+    scope.collect(&file.items);
+    let mut paths = PathCollector::default();
+    paths.visit_file(&file);
+
+    for (path, called) in &paths.paths {
+        let Some(full) = scope.expand(path) else {
+            continue;
+        };
+        if let Some(program) = programs.iter().find(|p| p.name == full[0]) {
+            report.checked += 1;
+            if full.len() >= 2 && !program.exports.contains(&full[1]) {
+                let last = &full[full.len() - 1];
+                report
+                    .findings
+                    .push(if *called && program.handlers.contains(last) {
+                        Finding::HandlerAsClient {
+                            path: full.join("::"),
+                            program: program.name.clone(),
+                        }
+                    } else {
+                        Finding::MissingItem {
+                            path: full.join("::"),
+                            segment: full[1].clone(),
+                        }
+                    });
+            }
+            continue;
+        }
+        let candidates: Vec<(Vec<String>, usize)> = if scope.is_glob_candidate(path) {
+            scope
+                .globs
+                .iter()
+                .map(|base| (base.iter().chain(path).cloned().collect(), base.len()))
+                .collect()
+        } else {
+            vec![(full, 0)]
+        };
+        match resolve_any(docs, &candidates) {
+            Some((candidate, Lookup::Found { trait_name })) => {
+                report.checked += 1;
+                if let Some(trait_name) = trait_name {
+                    if *called && !scope.has_trait(docs, &trait_name) {
+                        report.findings.push(Finding::TraitNotInScope {
+                            path: candidate.join("::"),
+                            trait_name,
+                        });
+                    }
+                }
+            }
+            Some((candidate, Lookup::Missing { at })) => {
+                report.checked += 1;
+                report.findings.push(Finding::MissingItem {
+                    path: candidate.join("::"),
+                    segment: candidate[at].clone(),
+                });
+            }
+            _ => report.unchecked += 1,
+        }
+    }
+
+    let mut seen = BTreeSet::new();
+    for literal in pubkey_literals(&file) {
+        if !seen.insert(literal.clone()) {
+            continue;
+        }
+        report.checked += 1;
+        if let Err(reason) = check_pubkey(&literal) {
+            report
+                .findings
+                .push(Finding::InvalidPubkey { literal, reason });
+        }
+    }
+
+    report.findings.dedup();
+    if report.checked > 0 {
+        let rate = report.findings.len() as f64 / report.checked as f64;
+        report.hallucination_rate = (rate * 1000.0).round() / 1000.0;
+    }
+    report
+}
+
+/// Resolves the first candidate the docs can judge. Each candidate comes
+/// with the index its own first segment sits at; for a glob candidate,
+/// going missing there only shows the name is not in that glob.
+fn resolve_any(
+    docs: &mut Docs,
+    candidates: &[(Vec<String>, usize)],
+) -> Option<(Vec<String>, Lookup)> {
+    for (candidate, start) in candidates {
+        match docs.resolve(candidate) {
+            Lookup::Unknown => continue,
+            Lookup::Missing { at } if *start > 0 && at <= *start => continue,
+            lookup => return Some((candidate.clone(), lookup)),
+        }
+    }
+    None
+}
+
+/// Whether `literal` decodes from base58 to 32 bytes.
+pub fn check_pubkey(literal: &str) -> Result<(), String> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in literal.chars() {
+        let Some(digit) = BASE58_ALPHABET.find(c) else {
+            return Err(format!("`{}` is not a base58 character", c));
+        };
+        let mut carry = digit as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as u32 * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, (carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    let leading_zeros = literal.chars().take_while(|&c| c == '1').count();
+    let len = leading_zeros + bytes.len();
+    if len == 32 {
+        Ok(())
+    } else {
+        Err(format!("decodes to {} bytes, not 32", len))
+    }
+}
+
+/// Names the snippet binds itself, and where its imports point.
+#[derive(Default)]
+struct Scope {
+    local: HashSet<String>,
+    imports: HashMap<String, Vec<String>>,
+    globs: Vec<Vec<String>>,
+}
+
+impl Scope {
+    fn collect(&mut self, items: &[Item]) {
+        for item in items {
+            if let Some(name) = item_name(item) {
+                self.local.insert(name);
+            }
+            match item {
+                Item::Use(u) => self.import(&u.tree, Vec::new()),
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.collect(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn import(&mut self, tree: &UseTree, prefix: Vec<String>) {
+        let with = |name: &syn::Ident| {
+            let mut path = prefix.clone();
+            path.push(name.to_string());
+            path
+        };
+        match tree {
+            UseTree::Path(p) => self.import(&p.tree, with(&p.ident)),
+            UseTree::Name(n) if n.ident == "self" => {
+                if let Some(last) = prefix.last() {
+                    self.imports.insert(last.clone(), prefix.clone());
+                }
+            }
+            UseTree::Name(n) => {
+                self.imports.insert(n.ident.to_string(), with(&n.ident));
+            }
+            UseTree::Rename(r) => {
+                self.imports.insert(r.rename.to_string(), with(&r.ident));
+            }
+            UseTree::Glob(_) => {
+                if prefix.first().is_some_and(|root| !is_relative(root)) {
+                    self.globs.push(prefix);
+                }
+            }
+            UseTree::Group(g) => {
+                for tree in &g.items {
+                    self.import(tree, prefix.clone());
+                }
+            }
+        }
+    }
+
+    /// The crate-rooted form of `path`, or `None` when it is local,
+    /// relative or rooted in std.
+    fn expand(&self, path: &[String]) -> Option<Vec<String>> {
+        let root = path.first()?;
+        if is_relative(root) || self.local.contains(root) {
+            return None;
+        }
+        let full: Vec<String> = match self.imports.get(root) {
+            Some(import) => import.iter().chain(&path[1..]).cloned().collect(),
+            None => path.to_vec(),
+        };
+        let root = full.first()?;
+        if is_relative(root) || BUILTIN_ROOTS.contains(&root.as_str()) {
+            return None;
+        }
+        Some(full)
+    }
+
+    /// A capitalised root that is neither imported nor local may come from a
+    /// glob import.
+    fn is_glob_candidate(&self, path: &[String]) -> bool {
+        path[0].starts_with(|c: char| c.is_ascii_uppercase())
+            && !self.imports.contains_key(&path[0])
+    }
+
+    fn has_trait(&self, docs: &mut Docs, name: &str) -> bool {
+        PRELUDE_TRAITS.contains(&name)
+            || self.imports.contains_key(name)
+            || self.local.contains(name)
+            || self.globs.iter().any(|base| {
+                let mut path = base.clone();
+                path.push(name.to_string());
+                !matches!(docs.resolve(&path), Lookup::Missing { .. })
+            })
+    }
+}
+
+fn is_relative(root: &str) -> bool {
+    matches!(root, "self" | "super" | "crate" | "Self")
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    let ident = match item {
+        Item::Const(i) => &i.ident,
+        Item::Enum(i) => &i.ident,
+        Item::Fn(i) => &i.sig.ident,
+        Item::Mod(i) => &i.ident,
+        Item::Static(i) => &i.ident,
+        Item::Struct(i) => &i.ident,
+        Item::Trait(i) => &i.ident,
+        Item::Type(i) => &i.ident,
+        Item::Union(i) => &i.ident,
+        Item::Macro(i) => i.ident.as_ref()?,
+        _ => return None,
+    };
+    Some(ident.to_string())
+}
+
+/// Every path the snippet names, with whether it is the callee of a call.
+#[derive(Default)]
+struct PathCollector {
+    paths: Vec<(Vec<String>, bool)>,
+}
+
+impl PathCollector {
+    fn push(&mut self, path: Vec<String>, called: bool) {
+        if path.len() < 2 {
+            return;
+        }
+        match self.paths.iter_mut().find(|(p, _)| *p == path) {
+            Some(entry) => entry.1 |= called,
+            None => self.paths.push((path, called)),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PathCollector {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut expanded = Vec::new();
+        use_paths(&item.tree, Vec::new(), &mut expanded);
+        for path in expanded {
+            self.push(path, false);
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(p) = &*call.func {
+            self.push(segments(&p.path), true);
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        self.push(segments(path), false);
+        visit::visit_path(self, path);
+    }
+}
+
+fn segments(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.ident.to_string()).collect()
+}
+
+/// Expands a use tree into the paths it names; a glob names its module.
+fn use_paths(tree: &UseTree, prefix: Vec<String>, out: &mut Vec<Vec<String>>) {
+    let with = |name: &syn::Ident| {
+        let mut path = prefix.clone();
+        path.push(name.to_string());
+        path
+    };
+    match tree {
+        UseTree::Path(p) => use_paths(&p.tree, with(&p.ident), out),
+        UseTree::Name(n) if n.ident == "self" => out.push(prefix),
+        UseTree::Name(n) => out.push(with(&n.ident)),
+        UseTree::Rename(r) => out.push(with(&r.ident)),
+        UseTree::Glob(_) => out.push(prefix),
+        UseTree::Group(g) => {
+            for tree in &g.items {
+                use_paths(tree, prefix.clone(), out);
+            }
+        }
+    }
+}
+
+/// String literals the snippet uses as pubkeys.
+fn pubkey_literals(file: &syn::File) -> Vec<String> {
+    let mut consts = ConstCollector::default();
+    consts.visit_file(file);
+    let mut collector = PubkeyCollector {
+        consts: consts.consts,
+        literals: Vec::new(),
+    };
+    collector.visit_file(file);
+    collector.literals
+}
+
+/// `const NAME: &str = "..."` items.
+#[derive(Default)]
+struct ConstCollector {
+    consts: HashMap<String, String>,
+}
+
+impl<'ast> Visit<'ast> for ConstCollector {
+    fn visit_item_const(&mut self, item: &'ast syn::ItemConst) {
+        if let Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(s), ..
+        }) = &*item.expr
+        {
+            self.consts.insert(item.ident.to_string(), s.value());
+        }
+        visit::visit_item_const(self, item);
+    }
+}
+
+struct PubkeyCollector {
+    consts: HashMap<String, String>,
+    literals: Vec<String>,
+}
+
+impl PubkeyCollector {
+    fn argument(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(s), ..
+            }) => self.literals.push(s.value()),
+            Expr::Path(p) => {
+                if let Some(value) = p
+                    .path
+                    .get_ident()
+                    .and_then(|i| self.consts.get(&i.to_string()))
+                {
+                    self.literals.push(value.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PubkeyCollector {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let name = mac.path.segments.last().map(|s| s.ident.to_string());
+        if matches!(name.as_deref(), Some("declare_id" | "pubkey")) {
+            if let Ok(expr) = mac.parse_body::<Expr>() {
+                self.argument(&expr);
+            }
+        }
+        visit::visit_macro(self, mac);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(p) = &*call.func {
+            let path = segments(&p.path);
+            let named = path.len() >= 2
+                && path[path.len() - 2] == "Pubkey"
+                && matches!(
+                    path[path.len() - 1].as_str(),
+                    "from_str" | "from_str_const" | "try_from"
+                );
+            if named {
+                if let Some(arg) = call.args.first() {
+                    self.argument(arg);
+                }
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::split_file;
+
+    fn solana() -> Vec<Snippet> {
+        split_file(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-solana.rs")).unwrap()
+    }
+
+    fn literals(code: &str) -> Vec<String> {
+        pubkey_literals(&syn::parse_file(code).unwrap())
+    }
+
+    #[test]
+    fn pubkeys_must_be_base58_for_32_bytes() {
+        assert_eq!(check_pubkey("11111111111111111111111111111111"), Ok(()));
+        assert_eq!(
+            check_pubkey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+            Ok(())
+        );
+        assert_eq!(
+            check_pubkey("1111111111111111111111111111111"),
+            Err("decodes to 31 bytes, not 32".to_string())
+        );
+        assert_eq!(
+            check_pubkey("So11111111111111111111111111111111111111112"),
+            Ok(())
+        );
+        assert_eq!(
+            check_pubkey("Token0"),
+            Err("`0` is not a base58 character".to_string())
+        );
+    }
+
+    #[test]
+    fn finds_pubkey_literals_in_macros_calls_and_consts() {
+        let found = literals(
+            "declare_id!(\"11111111111111111111111111111111\");\n\
+             const MINT: &str = \"So11111111111111111111111111111111111111112\";\n\
+             fn main() {\n    let _ = Pubkey::from_str(MINT);\n    \
+             let _ = pubkey!(\"Vote111111111111111111111111111111111111111\");\n    \
+             let _ = String::from(\"not a key\");\n}\n",
+        );
+        assert_eq!(
+            found,
+            [
+                "11111111111111111111111111111111",
+                "So11111111111111111111111111111111111111112",
+                "Vote111111111111111111111111111111111111111"
+            ]
+        );
+    }
+
+    #[test]
+    fn sample_placeholder_ids() {
+        let snippets = solana();
+        let nft = literals(&snippets[8].code);
+        assert_eq!(nft, ["NFTMeta1111111111111111111111111111111111"]);
+        assert_eq!(
+            check_pubkey(&nft[0]),
+            Err("decodes to 30 bytes, not 32".to_string())
+        );
+
+        let token = literals(&snippets[7].code);
+        assert_eq!(
+            check_pubkey(&token[0]),
+            Err("`l` is not a base58 character".to_string())
+        );
+        let counter = literals(&snippets[0].code);
+        assert_eq!(check_pubkey(&counter[0]), Ok(()));
+    }
+
+    #[test]
+    fn sample_programs_and_handlers() {
+        let programs = programs(&solana());
+        let counter = programs.iter().find(|p| p.name == "counter").unwrap();
+        assert!(counter.handlers.contains("increment"));
+        assert!(counter.exports.contains("ID"));
+    }
+}
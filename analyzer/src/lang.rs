@@ -0,0 +1,239 @@
+//! Offline natural-language tagging of snippet comments.
+//!
+//! The corpus is commented in English or Turkish, so a two-way classifier is
+//! enough: words that contain Turkish-only letters or appear in the Turkish
+//! function-word list count for Turkish, words in the English list count for
+//! English, and everything else (identifiers, numbers) is ignored. The
+//! snippet is tagged with the language holding most of the evidence and
+//! flagged as mixed when the other language holds a real share of it.
+
+use serde::Serialize;
+
+use crate::comments::{self, CommentKind};
+use crate::snippet::Snippet;
+
+/// Share of the evidence the minority language needs for a snippet to count
+/// as mixed.
+pub const MIXED_SHARE: f64 = 0.15;
+
+/// Letters that occur in Turkish but not in English.
+const TURKISH_LETTERS: &[char] = &['ç', 'ğ', 'ı', 'ö', 'ş', 'ü', 'Ç', 'Ğ', 'İ', 'Ö', 'Ş', 'Ü'];
+
+const TURKISH_WORDS: &[&str] = &[
+    "acaba",
+    "ama",
+    "ancak",
+    "bir",
+    "biz",
+    "bu",
+    "da",
+    "daha",
+    "de",
+    "dizi",
+    "diziyi",
+    "en",
+    "eleman",
+    "elemanlar",
+    "fonksiyon",
+    "fonksiyonu",
+    "gibi",
+    "her",
+    "ile",
+    "ise",
+    "kadar",
+    "mi",
+    "ne",
+    "olan",
+    "olarak",
+    "sadece",
+    "sonra",
+    "tipinde",
+    "ve",
+    "veri",
+    "veya",
+    "ya",
+    "yap",
+    "yeni",
+];
+
+const ENGLISH_WORDS: &[&str] = &[
+    "a", "all", "an", "and", "are", "as", "at", "be", "by", "can", "create", "each", "for", "from",
+    "if", "in", "into", "is", "it", "its", "not", "of", "on", "or", "our", "print", "return",
+    "returns", "should", "that", "the", "then", "this", "to", "use", "uses", "using", "we", "when",
+    "which", "will", "with", "you", "your",
+];
+
+/// Natural language of a comment or snippet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Tr,
+    /// No comment carried enough signal to decide.
+    Unknown,
+}
+
+/// The comment-language tag for one snippet.
+#[derive(Debug, Clone, Serialize)]
+pub struct LanguageTag {
+    pub id: String,
+    pub language: Language,
+    /// Both languages hold at least [`MIXED_SHARE`] of the evidence.
+    pub mixed: bool,
+    pub english_comments: usize,
+    pub turkish_comments: usize,
+    pub unclassified_comments: usize,
+}
+
+/// Tags `snippet` from its doc comments and line comments. Block comments,
+/// which include the banner, are left to the banner metadata.
+pub fn tag(snippet: &Snippet) -> LanguageTag {
+    let mut tag = LanguageTag {
+        id: snippet.id.clone(),
+        language: Language::Unknown,
+        mixed: false,
+        english_comments: 0,
+        turkish_comments: 0,
+        unclassified_comments: 0,
+    };
+    let (mut english, mut turkish) = (0, 0);
+    for comment in comments::extract(&snippet.code) {
+        if comment.kind == CommentKind::Block {
+            continue;
+        }
+        let (en, tr) = score(&comment.text);
+        english += en;
+        turkish += tr;
+        match classify_scores(en, tr) {
+            Language::En => tag.english_comments += 1,
+            Language::Tr => tag.turkish_comments += 1,
+            Language::Unknown => tag.unclassified_comments += 1,
+        }
+    }
+    tag.language = classify_scores(english, turkish);
+    let total = (english + turkish) as f64;
+    if total > 0.0 {
+        let minority = english.min(turkish) as f64;
+        tag.mixed = minority / total >= MIXED_SHARE;
+    }
+    tag
+}
+
+/// Classifies a single piece of text.
+pub fn classify(text: &str) -> Language {
+    let (en, tr) = score(text);
+    classify_scores(en, tr)
+}
+
+fn classify_scores(en: usize, tr: usize) -> Language {
+    match en.cmp(&tr) {
+        std::cmp::Ordering::Greater => Language::En,
+        std::cmp::Ordering::Less => Language::Tr,
+        std::cmp::Ordering::Equal => Language::Unknown,
+    }
+}
+
+/// Turkish attaches case suffixes to names with an apostrophe (`Stack'e`,
+/// `trait'i`); English only does so for `'s` and contractions.
+fn has_turkish_suffix(word: &str) -> bool {
+    match word.rsplit_once('\'') {
+        Some((stem, suffix)) => {
+            !stem.is_empty()
+                && !suffix.is_empty()
+                && !matches!(suffix, "s" | "t" | "re" | "ll" | "ve" | "d" | "m")
+        }
+        None => false,
+    }
+}
+
+/// Counts `(english, turkish)` evidence words in `text`.
+fn score(text: &str) -> (usize, usize) {
+    let (mut en, mut tr) = (0, 0);
+    for word in text.split(|c: char| !c.is_alphabetic() && c != '\'') {
+        let word = word.trim_matches('\'');
+        if word.is_empty() {
+            continue;
+        }
+        if word.contains(TURKISH_LETTERS) || has_turkish_suffix(word) {
+            tr += 1;
+            continue;
+        }
+        let lower = word.to_lowercase();
+        if TURKISH_WORDS.contains(&lower.as_str()) {
+            tr += 1;
+        } else if ENGLISH_WORDS.contains(&lower.as_str()) {
+            en += 1;
+        }
+    }
+    (en, tr)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::{split_file, split_source};
+
+    fn tag_source(source: &str) -> LanguageTag {
+        let snippets = split_source(Path::new("fixture.rs"), source).unwrap();
+        tag(&snippets[0])
+    }
+
+    #[test]
+    fn classifies_single_comments() {
+        assert_eq!(classify("Returns the sum of all the numbers"), Language::En);
+        assert_eq!(classify("Diziyi sıralar ve ekrana yazar"), Language::Tr);
+        assert_eq!(classify("Stack'e yeni eleman ekle"), Language::Tr);
+        // English contractions are not Turkish suffixes.
+        assert_eq!(classify("we'll print what's left"), Language::En);
+        assert_eq!(classify("x + 1"), Language::Unknown);
+    }
+
+    #[test]
+    fn tags_by_line_and_doc_comments_only() {
+        let tag = tag_source(
+            "/* Bu blok yorum sayılmaz ve bir ve bir */\n\
+             /// Returns the largest element of the slice.\n\
+             fn largest(v: &[i32]) -> i32 {\n    // Start with the first one\n    v[0] // TODO\n}\n",
+        );
+        assert_eq!(tag.language, Language::En);
+        assert!(!tag.mixed);
+        assert_eq!(
+            (
+                tag.english_comments,
+                tag.turkish_comments,
+                tag.unclassified_comments
+            ),
+            (2, 0, 1)
+        );
+    }
+
+    #[test]
+    fn flags_mixed_snippets() {
+        let tag = tag_source(
+            "// Bu fonksiyon diziyi sıralar\n\
+             // Sonra ekrana yazdırır\n\
+             // Prints the sorted array to the console\n\
+             fn main() {}\n",
+        );
+        assert_eq!(tag.language, Language::Tr);
+        assert!(tag.mixed);
+    }
+
+    #[test]
+    fn sample_turkish_snippets() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-full.rs");
+        let languages: Vec<Language> = split_file(&path)
+            .unwrap()
+            .iter()
+            .map(|s| tag(s).language)
+            .collect();
+        let turkish: Vec<usize> = (0..languages.len())
+            .filter(|&i| languages[i] == Language::Tr)
+            .map(|i| i + 1)
+            .collect();
+        assert_eq!(turkish, [11, 12, 13, 14, 15, 16]);
+        assert!(!languages.contains(&Language::Unknown));
+    }
+}
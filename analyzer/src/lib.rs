@@ -0,0 +1,26 @@
+//! Tooling for the o1 code-sample dataset.
+//!
+//! The samples in this repository are raw model output: several files hold
+//! many independent programs back to back. This crate cuts them into
+//! standalone snippets that the analyzer pipeline can build and label one by
+//! one.
+
+pub mod comments;
+pub mod corpus;
+pub mod dedup;
+pub mod deps;
+pub mod emit;
+pub mod error;
+pub mod export;
+pub mod hallucination;
+pub mod lang;
+pub mod manifest;
+pub mod metadata;
+pub mod rustdoc;
+pub mod snippet;
+pub mod stylometry;
+pub mod verify;
+
+pub use error::{Error, Result};
+pub use manifest::ManifestEntry;
+pub use snippet::{split_file, Banner, Snippet, SnippetKind};
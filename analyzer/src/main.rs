@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+
+use o1_analyzer::{
+    corpus, dedup, deps, emit, export, hallucination, lang, rustdoc, split_file, stylometry,
+    verify, Error, ManifestEntry, Result,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "o1-analyzer", version)]
+#[command(about = "Splits and inspects the o1-generated code samples")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Split multi-program Rust samples into one Cargo package per snippet
+    Split {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Directory that receives the generated packages
+        #[arg(short, long, default_value = "snippets")]
+        out: PathBuf,
+    },
+    /// Report crates each snippet uses and where its banner disagrees
+    Deps {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Build and run each snippet offline and check its claimed output
+    Verify {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Directory for generated packages, build output and run dirs
+        #[arg(short, long, default_value = "target/o1-verify")]
+        work: PathBuf,
+        /// Directory of fixture files overriding the built-in ones
+        #[arg(short, long)]
+        fixtures: Option<PathBuf>,
+        /// Seconds a program may run before it is killed
+        #[arg(short, long, default_value_t = 10)]
+        timeout: u64,
+        /// Print one JSON verdict per line instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Cluster duplicate and near-duplicate snippets by syntax-tree shape
+    Dedup {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Minimum similarity (0.0 to 1.0) for two snippets to be grouped
+        #[arg(short, long, default_value_t = 0.35)]
+        threshold: f64,
+        /// Print the clusters as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Tag each snippet with the natural language of its comments
+    Lang {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Print one JSON tag per line instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one stylometric feature vector per snippet as JSONL
+    Features {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Output file; the vectors go to stdout when omitted
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Index every o1-* sample under a directory into a SQLite database
+    Index {
+        /// Repository root to walk
+        #[arg(default_value = "..")]
+        root: PathBuf,
+        /// SQLite database that receives the index
+        #[arg(short, long, default_value = "o1-index.sqlite")]
+        out: PathBuf,
+    },
+    /// Export one instruction/response JSONL record per snippet
+    Export {
+        /// Repository root to walk
+        #[arg(default_value = "..")]
+        root: PathBuf,
+        /// Output file; the records go to stdout when omitted
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Build and run Rust snippets to fill in their verification status
+        #[arg(long)]
+        verify: bool,
+        /// Directory for generated packages, build output and run dirs
+        #[arg(short, long, default_value = "target/o1-verify")]
+        work: PathBuf,
+        /// Seconds a program may run before it is killed
+        #[arg(short, long, default_value_t = 10)]
+        timeout: u64,
+    },
+    /// Flag made-up APIs and invalid pubkeys by resolving paths in rustdoc JSON
+    Apis {
+        /// Rust sample files, e.g. o1-rust-solana.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Directory for generated packages and rustdoc output
+        #[arg(short, long, default_value = "target/o1-apis")]
+        work: PathBuf,
+        /// Only use rustdoc JSON already under <work>/target/doc
+        #[arg(long)]
+        no_generate: bool,
+        /// Print one JSON report per line instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a JSON manifest of every snippet and its banner metadata
+    Manifest {
+        /// Rust sample files, e.g. o1-rust-full.rs
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Output file; the manifest goes to stdout when omitted
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Split { files, out } => {
+            for file in &files {
+                for snippet in split_file(file)? {
+                    let dir = emit::write_package(&out, &snippet)?;
+                    println!(
+                        "{:<48} lines {:>4}-{:<4} -> {}",
+                        snippet.id,
+                        snippet.start_line,
+                        snippet.end_line,
+                        dir.display()
+                    );
+                }
+            }
+        }
+        Command::Deps { files } => {
+            for file in &files {
+                for snippet in split_file(file)? {
+                    let resolution = deps::resolve(&snippet);
+                    println!("{}", snippet.id);
+                    for line in deps::render(&resolution.dependencies).lines() {
+                        println!("    {}", line);
+                    }
+                    for mismatch in &resolution.mismatches {
+                        println!("    warning: {}", mismatch);
+                    }
+                    for package in &resolution.unresolved {
+                        println!(
+                            "    warning: `{}` has no pinned version and the banner gives none; left out",
+                            package
+                        );
+                    }
+                    for package in &resolution.local {
+                        println!(
+                            "    note: `{}` is defined by another snippet of this file; left out",
+                            package
+                        );
+                    }
+                }
+            }
+        }
+        Command::Verify {
+            files,
+            work,
+            fixtures,
+            timeout,
+            json,
+        } => {
+            let options = verify::Options {
+                work_dir: work,
+                fixtures,
+                timeout: Duration::from_secs(timeout),
+            };
+            for file in &files {
+                for snippet in split_file(file)? {
+                    let verdict = verify::verify(&snippet, &options)?;
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string(&verdict).expect("verdict is plain data")
+                        );
+                    } else {
+                        let status =
+                            serde_json::to_value(verdict.status).expect("status is plain data");
+                        println!(
+                            "{:<64} {:<12} {}/{}",
+                            verdict.id,
+                            status.as_str().unwrap_or_default(),
+                            verdict.matched_lines,
+                            verdict.expected_lines
+                        );
+                    }
+                }
+            }
+        }
+        Command::Dedup {
+            files,
+            threshold,
+            json,
+        } => {
+            let mut snippets = Vec::new();
+            for file in &files {
+                snippets.extend(split_file(file)?);
+            }
+            let clusters = dedup::cluster(&snippets, threshold);
+            if json {
+                let json =
+                    serde_json::to_string_pretty(&clusters).expect("clusters are plain data");
+                println!("{}", json);
+            } else {
+                for (n, cluster) in clusters.iter().enumerate() {
+                    println!(
+                        "cluster {} (min similarity {:.3})",
+                        n + 1,
+                        cluster.min_similarity
+                    );
+                    for pair in &cluster.pairs {
+                        println!("    {:.3}  {}  ~  {}", pair.similarity, pair.a, pair.b);
+                    }
+                }
+            }
+        }
+        Command::Lang { files, json } => {
+            for file in &files {
+                for snippet in split_file(file)? {
+                    let tag = lang::tag(&snippet);
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string(&tag).expect("tag is plain data")
+                        );
+                    } else {
+                        let language =
+                            serde_json::to_value(tag.language).expect("language is plain data");
+                        println!(
+                            "{:<64} {:<8} {:<6} en:{} tr:{} ?:{}",
+                            tag.id,
+                            language.as_str().unwrap_or_default(),
+                            if tag.mixed { "mixed" } else { "" },
+                            tag.english_comments,
+                            tag.turkish_comments,
+                            tag.unclassified_comments
+                        );
+                    }
+                }
+            }
+        }
+        Command::Features { files, out } => {
+            let mut jsonl = String::new();
+            for file in &files {
+                for snippet in split_file(file)? {
+                    let features = stylometry::extract(&snippet);
+                    jsonl.push_str(
+                        &serde_json::to_string(&features).expect("features are plain data"),
+                    );
+                    jsonl.push('\n');
+                }
+            }
+            match out {
+                Some(path) => fs::write(&path, jsonl).map_err(|e| Error::io(path, e))?,
+                None => print!("{}", jsonl),
+            }
+        }
+        Command::Index { root, out } => {
+            let mut entries = Vec::new();
+            for sample in corpus::discover(&root, Some(&out))? {
+                entries.extend(corpus::index_file(&root, &sample)?);
+            }
+            corpus::write_sqlite(&out, &entries)?;
+            println!("indexed {} snippets into {}", entries.len(), out.display());
+        }
+        Command::Export {
+            root,
+            out,
+            verify,
+            work,
+            timeout,
+        } => {
+            let options = verify.then(|| verify::Options {
+                work_dir: work,
+                fixtures: None,
+                timeout: Duration::from_secs(timeout),
+            });
+            let mut jsonl = String::new();
+            for sample in corpus::discover(&root, out.as_deref())? {
+                for record in export::records(&sample, options.as_ref())? {
+                    jsonl.push_str(&serde_json::to_string(&record).expect("record is plain data"));
+                    jsonl.push('\n');
+                }
+            }
+            match out {
+                Some(path) => fs::write(&path, jsonl).map_err(|e| Error::io(path, e))?,
+                None => print!("{}", jsonl),
+            }
+        }
+        Command::Apis {
+            files,
+            work,
+            no_generate,
+            json,
+        } => {
+            let mut docs = rustdoc::Docs::new(work.join("target"));
+            for file in &files {
+                let snippets = split_file(file)?;
+                let programs = hallucination::programs(&snippets);
+                for snippet in &snippets {
+                    let package = if no_generate {
+                        None
+                    } else {
+                        Some(emit::write_package(&work.join("packages"), snippet)?)
+                    };
+                    docs.set_package(package);
+                    let report = hallucination::check(snippet, &mut docs, &programs);
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::to_string(&report).expect("report is plain data")
+                        );
+                    } else {
+                        println!(
+                            "{:<64} rate {:.3} ({} checked, {} unchecked)",
+                            report.id, report.hallucination_rate, report.checked, report.unchecked
+                        );
+                        for finding in &report.findings {
+                            println!("    {}", finding);
+                        }
+                    }
+                }
+            }
+        }
+        Command::Manifest { files, out } => {
+            let mut entries = Vec::new();
+            for file in &files {
+                entries.extend(split_file(file)?.iter().map(ManifestEntry::new));
+            }
+            let mut json = serde_json::to_string_pretty(&entries).expect("manifest is plain data");
+            json.push('\n');
+            match out {
+                Some(path) => fs::write(&path, json).map_err(|e| Error::io(path, e))?,
+                None => print!("{}", json),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! The per-snippet JSON manifest used to filter and label samples.
+
+use serde::Serialize;
+
+use crate::metadata::{self, Metadata};
+use crate::snippet::{Snippet, SnippetKind};
+
+/// One manifest record: where a snippet lives plus its banner metadata.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub source: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub kind: &'static str,
+    /// `None` for snippets that have no banner.
+    #[serde(flatten)]
+    pub metadata: Option<Metadata>,
+}
+
+impl ManifestEntry {
+    pub fn new(snippet: &Snippet) -> Self {
+        ManifestEntry {
+            id: snippet.id.clone(),
+            source: snippet.source.display().to_string(),
+            start_line: snippet.start_line,
+            end_line: snippet.end_line,
+            kind: kind_name(snippet.kind),
+            metadata: snippet.banner.as_ref().map(metadata::parse),
+        }
+    }
+}
+
+pub(crate) fn kind_name(kind: SnippetKind) -> &'static str {
+    match kind {
+        SnippetKind::Binary => "bin",
+        SnippetKind::Library => "lib",
+        SnippetKind::Test => "test",
+    }
+}
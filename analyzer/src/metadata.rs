@@ -0,0 +1,372 @@
+//! Parsing of the structured sections inside snippet banners.
+//!
+//! Banners follow one of two layouts:
+//!
+//! ```text
+//! Purpose:                    Amaç:
+//! Crates/Dependencies:        Gereksinimler:
+//! Instructions to Run:        Çalıştırma Talimatları:
+//! Example Output:             Beklenen Çıktı:
+//! ```
+//!
+//! Section headings start in column zero and end with a colon; their content
+//! is indented below them.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::snippet::Banner;
+
+/// Natural language of a banner, judged by its section headings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerLanguage {
+    En,
+    Tr,
+    Unknown,
+}
+
+/// A crate named in a banner, with the version it pins if any.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    pub name: String,
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+}
+
+/// Everything a banner says about its snippet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Metadata {
+    pub title: String,
+    pub language: BannerLanguage,
+    pub purpose: Option<String>,
+    pub dependencies: Vec<Dependency>,
+    pub run_instructions: Option<String>,
+    pub expected_output: Option<String>,
+    /// Sections without a dedicated field, keyed by their heading
+    /// (`Notes`, `Example Usage`, ...).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub other_sections: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Purpose,
+    Dependencies,
+    Run,
+    ExpectedOutput,
+    Other,
+}
+
+/// Known headings, lowercased, with the field and language they indicate.
+const HEADINGS: &[(&str, Field, BannerLanguage)] = &[
+    ("purpose", Field::Purpose, BannerLanguage::En),
+    (
+        "crates/dependencies",
+        Field::Dependencies,
+        BannerLanguage::En,
+    ),
+    ("dependencies", Field::Dependencies, BannerLanguage::En),
+    ("instructions to run", Field::Run, BannerLanguage::En),
+    ("usage", Field::Run, BannerLanguage::En),
+    ("example output", Field::ExpectedOutput, BannerLanguage::En),
+    ("notes", Field::Other, BannerLanguage::En),
+    ("note", Field::Other, BannerLanguage::En),
+    ("amaç", Field::Purpose, BannerLanguage::Tr),
+    ("gereksinimler", Field::Dependencies, BannerLanguage::Tr),
+    (
+        "kullanılacak krate",
+        Field::Dependencies,
+        BannerLanguage::Tr,
+    ),
+    ("cargo.toml örnek", Field::Dependencies, BannerLanguage::Tr),
+    ("çalıştırma talimatları", Field::Run, BannerLanguage::Tr),
+    ("beklenen çıktı", Field::ExpectedOutput, BannerLanguage::Tr),
+];
+
+/// Parses the sections of `banner`.
+pub fn parse(banner: &Banner) -> Metadata {
+    let mut metadata = Metadata {
+        title: banner.title.clone(),
+        language: BannerLanguage::Unknown,
+        purpose: None,
+        dependencies: Vec::new(),
+        run_instructions: None,
+        expected_output: None,
+        other_sections: BTreeMap::new(),
+    };
+    let mut dependency_text = Vec::new();
+    for (heading, text) in sections(&banner.body) {
+        let known = HEADINGS
+            .iter()
+            .find(|(h, _, _)| *h == heading.to_lowercase());
+        let field = match known {
+            Some((_, field, language)) => {
+                if metadata.language == BannerLanguage::Unknown {
+                    metadata.language = *language;
+                }
+                *field
+            }
+            None => Field::Other,
+        };
+        match field {
+            Field::Purpose => metadata.purpose = Some(text),
+            Field::Dependencies => dependency_text.push(text),
+            Field::Run => metadata.run_instructions = Some(text),
+            Field::ExpectedOutput => metadata.expected_output = Some(text),
+            Field::Other => {
+                metadata.other_sections.insert(heading, text);
+            }
+        }
+    }
+    // Versions are pinned wherever a `[dependencies]` block appears, which is
+    // sometimes under `Usage:` rather than the dependency section.
+    metadata.dependencies = toml_dependencies(&banner.body);
+    for text in &dependency_text {
+        for name in mentioned_crates(text) {
+            if !metadata
+                .dependencies
+                .iter()
+                .any(|d| same_crate(&d.name, &name))
+            {
+                metadata.dependencies.push(Dependency {
+                    name,
+                    version: None,
+                    features: Vec::new(),
+                });
+            }
+        }
+    }
+    metadata
+}
+
+/// Splits a banner body into `(heading, dedented text)` pairs.
+fn sections(body: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, Vec<&str>)> = Vec::new();
+    for line in body.lines() {
+        let is_heading = !line.starts_with(char::is_whitespace) && line.trim_end().ends_with(':');
+        if is_heading {
+            let heading = line.trim_end().trim_end_matches(':').trim().to_string();
+            sections.push((heading, Vec::new()));
+        } else if let Some((_, lines)) = sections.last_mut() {
+            lines.push(line);
+        }
+    }
+    sections
+        .into_iter()
+        .map(|(heading, lines)| (heading, dedent(&lines)))
+        .collect()
+}
+
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let text: Vec<&str> = lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or("").trim_end())
+        .collect();
+    text.join("\n").trim_matches('\n').to_string()
+}
+
+/// Reads `name = "1"` and `name = { version = "4", ... }` lines.
+fn toml_dependencies(body: &str) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    for line in body.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        let value = value.trim();
+        let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_name || !(value.starts_with('"') || value.starts_with('{')) {
+            continue;
+        }
+        let Ok(table) = format!("{} = {}", name, value).parse::<toml::Table>() else {
+            continue;
+        };
+        let dependency = match table.get(name) {
+            Some(toml::Value::String(version)) => Dependency {
+                name: name.to_string(),
+                version: Some(version.clone()),
+                features: Vec::new(),
+            },
+            Some(toml::Value::Table(spec)) => Dependency {
+                name: name.to_string(),
+                version: spec
+                    .get("version")
+                    .and_then(|v| v.as_str())
+                    .map(String::from),
+                features: spec
+                    .get("features")
+                    .and_then(|v| v.as_array())
+                    .map(|a| {
+                        a.iter()
+                            .filter_map(|f| f.as_str().map(String::from))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            _ => continue,
+        };
+        if !dependencies
+            .iter()
+            .any(|d: &Dependency| d.name == dependency.name)
+        {
+            dependencies.push(dependency);
+        }
+    }
+    dependencies
+}
+
+/// Crate names mentioned in prose, e.g. `- anchor-lang for building ...`,
+/// `solana-sdk, solana-client` or ``1. `Rocket` krate'ini kullanır``.
+fn mentioned_crates(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') || line.starts_with("None") || line.contains('=') {
+            continue;
+        }
+        let words: Vec<&str> = line
+            .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-')
+            .split_whitespace()
+            .collect();
+        for (i, word) in words.iter().enumerate() {
+            let backticked = word.starts_with('`');
+            let clean =
+                word.trim_matches(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-');
+            let dotted = word.trim_end_matches(['.', ',', ':']).contains('.');
+            if clean.is_empty() || word.contains("::") || dotted {
+                continue;
+            }
+            let next = words.get(i + 1).copied().unwrap_or("");
+            let looks_like_crate = backticked
+                || clean.contains(['-', '_'])
+                || (i > 0 && words[i - 1] == "Add")
+                || (i == 0 && (matches!(next, "for" | "ve") || next.starts_with('(')));
+            let name = clean.to_lowercase();
+            if looks_like_crate
+                && name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && !names.iter().any(|n| same_crate(n, &name))
+            {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Cargo treats `-` and `_` in crate names as equivalent.
+pub fn same_crate(a: &str, b: &str) -> bool {
+    a.replace('-', "_") == b.replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::split_file;
+
+    fn banner(body: &str) -> Banner {
+        Banner {
+            title: "Title".to_string(),
+            body: body.to_string(),
+            start_line: 1,
+            end_line: 1,
+        }
+    }
+
+    fn names(dependencies: &[Dependency]) -> Vec<&str> {
+        dependencies.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn english_sections() {
+        let metadata = parse(&banner(
+            "Purpose:\n    Sum numbers on several threads.\n\
+             Crates/Dependencies:\n    - rayon for parallel iterators\n\
+             Instructions to Run:\n    1. cargo run\n\
+             Example Output:\n    The total sum is: 4950\n\
+             Notes:\n    Nothing else.\n",
+        ));
+        assert_eq!(metadata.language, BannerLanguage::En);
+        assert_eq!(
+            metadata.purpose.as_deref(),
+            Some("Sum numbers on several threads.")
+        );
+        assert_eq!(names(&metadata.dependencies), ["rayon"]);
+        assert_eq!(metadata.run_instructions.as_deref(), Some("1. cargo run"));
+        assert_eq!(
+            metadata.expected_output.as_deref(),
+            Some("The total sum is: 4950")
+        );
+        assert_eq!(metadata.other_sections["Notes"], "Nothing else.");
+    }
+
+    #[test]
+    fn turkish_sections() {
+        let metadata = parse(&banner(
+            "Amaç:\n  Diziyi sıralar.\n\
+             Gereksinimler:\n  1. `Rocket` krate'ini kullanır\n  serde ve serde_json\n\
+             Çalıştırma Talimatları:\n  cargo run\n\
+             Beklenen Çıktı:\n  Sıralanmış dizi ekrana yazılır.\n",
+        ));
+        assert_eq!(metadata.language, BannerLanguage::Tr);
+        assert_eq!(metadata.purpose.as_deref(), Some("Diziyi sıralar."));
+        assert_eq!(
+            names(&metadata.dependencies),
+            ["rocket", "serde", "serde_json"]
+        );
+        assert_eq!(metadata.run_instructions.as_deref(), Some("cargo run"));
+        assert_eq!(
+            metadata.expected_output.as_deref(),
+            Some("Sıralanmış dizi ekrana yazılır.")
+        );
+    }
+
+    #[test]
+    fn toml_blocks_pin_versions_and_features() {
+        let metadata = parse(&banner(
+            "Usage:\n    [dependencies]\n    tokio = { version = \"1\", features = [\"full\"] }\n    \
+             reqwest = \"0.11\" // blocking client\n\
+             Crates/Dependencies:\n    tokio, reqwest and `scraper`\n",
+        ));
+        let tokio = &metadata.dependencies[0];
+        assert_eq!(tokio.version.as_deref(), Some("1"));
+        assert_eq!(tokio.features, ["full"]);
+        assert_eq!(metadata.dependencies[1].version.as_deref(), Some("0.11"));
+        // Prose mentions add crates the block left out, without duplicates.
+        assert_eq!(
+            names(&metadata.dependencies),
+            ["tokio", "reqwest", "scraper"]
+        );
+    }
+
+    #[test]
+    fn sample_banners() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-full.rs");
+        let snippets = split_file(&path).unwrap();
+        let metadata = |i: usize| parse(snippets[i].banner.as_ref().unwrap());
+        assert_eq!(metadata(0).language, BannerLanguage::En);
+        assert_eq!(metadata(0).expected_output.as_deref(), Some("5! = 120"));
+        assert_eq!(metadata(10).language, BannerLanguage::Tr);
+        assert!(snippets[16].banner.is_none());
+    }
+
+    #[test]
+    fn dashes_and_underscores_name_the_same_crate() {
+        assert!(same_crate("anchor-lang", "anchor_lang"));
+        assert!(!same_crate("anchor-lang", "anchor-spl"));
+    }
+}
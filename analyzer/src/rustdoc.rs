@@ -0,0 +1,333 @@
+//! Path lookup against rustdoc's JSON output.
+//!
+//! `cargo +nightly rustdoc -- -Z unstable-options --output-format json`
+//! describes every public item of a crate, including re-exports that point
+//! into other crates. [`Docs`] loads those files on demand, generating any
+//! that are missing inside a snippet's package, and walks `a::b::c` paths
+//! through modules, `use` re-exports, glob imports and impl blocks the way
+//! name resolution would.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+/// Re-exports can chain through globs; stop following them after this many.
+const MAX_DEPTH: usize = 16;
+
+/// How far a path got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup {
+    /// The path names an item. `trait_name` is set when the last segment is
+    /// an associated item that only a trait impl provides.
+    Found { trait_name: Option<String> },
+    /// Segment `at` (0-based) does not exist in the item before it.
+    Missing { at: usize },
+    /// Documentation for some crate on the way is unavailable, or the path
+    /// continues past something the docs do not describe.
+    Unknown,
+}
+
+/// One crate's rustdoc JSON.
+struct CrateDoc {
+    doc: Value,
+    /// Canonical paths of the crate's own items, for following re-exports
+    /// from other crates.
+    own_paths: HashMap<Vec<String>, String>,
+}
+
+impl CrateDoc {
+    fn new(doc: Value) -> Self {
+        let mut own_paths = HashMap::new();
+        if let Some(paths) = doc["paths"].as_object() {
+            for (id, summary) in paths {
+                if summary["crate_id"].as_u64() != Some(0) {
+                    continue;
+                }
+                let path: Vec<String> = summary["path"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|s| s.as_str().map(str::to_string))
+                    .collect();
+                own_paths.insert(path, id.clone());
+            }
+        }
+        CrateDoc { doc, own_paths }
+    }
+
+    fn item(&self, id: &str) -> Option<&Value> {
+        self.doc["index"].get(id)
+    }
+
+    fn root(&self) -> String {
+        id_key(&self.doc["root"]).unwrap_or_default()
+    }
+}
+
+/// Where the next segment of a path led.
+enum Step {
+    Item { krate: String, id: String },
+    Assoc { trait_name: Option<String> },
+    Missing,
+    Unknown,
+}
+
+/// A lazily loaded set of crate docs.
+pub struct Docs {
+    target_dir: PathBuf,
+    /// Package whose dependency graph is used to generate missing docs.
+    package: Option<PathBuf>,
+    crates: HashMap<String, Option<CrateDoc>>,
+}
+
+impl Docs {
+    /// Reads and writes docs under `<target_dir>/doc`.
+    pub fn new(target_dir: impl Into<PathBuf>) -> Self {
+        Docs {
+            target_dir: target_dir.into(),
+            package: None,
+            crates: HashMap::new(),
+        }
+    }
+
+    /// Generates missing docs with `package`'s dependencies from now on;
+    /// `None` only reads what is already on disk.
+    pub fn set_package(&mut self, package: Option<PathBuf>) {
+        if package.is_some() {
+            // Crates that were unavailable may be in the new graph.
+            self.crates.retain(|_, doc| doc.is_some());
+        }
+        self.package = package;
+    }
+
+    /// Resolves `path`, whose first segment is a crate name.
+    pub fn resolve(&mut self, path: &[String]) -> Lookup {
+        let Some(first) = path.first() else {
+            return Lookup::Unknown;
+        };
+        let krate = first.replace('-', "_");
+        let Some(root) = self.load(&krate).map(CrateDoc::root) else {
+            return Lookup::Unknown;
+        };
+        let (mut krate, mut id) = (krate, root);
+        for (at, segment) in path.iter().enumerate().skip(1) {
+            match self.child(&krate, &id, segment, 0) {
+                Step::Item {
+                    krate: next_krate,
+                    id: next_id,
+                } => {
+                    krate = next_krate;
+                    id = next_id;
+                }
+                Step::Assoc { trait_name } if at + 1 == path.len() => {
+                    return Lookup::Found { trait_name };
+                }
+                Step::Assoc { .. } | Step::Unknown => return Lookup::Unknown,
+                Step::Missing => return Lookup::Missing { at },
+            }
+        }
+        Lookup::Found { trait_name: None }
+    }
+
+    /// Looks `name` up inside item `id` of `krate`.
+    fn child(&mut self, krate: &str, id: &str, name: &str, depth: usize) -> Step {
+        if depth > MAX_DEPTH {
+            return Step::Unknown;
+        }
+        let Some(doc) = self.load(krate) else {
+            return Step::Unknown;
+        };
+        let Some(item) = doc.item(id) else {
+            return Step::Unknown;
+        };
+        let Some((kind, inner)) = item["inner"].as_object().and_then(|o| o.iter().next()) else {
+            return Step::Unknown;
+        };
+        match kind.as_str() {
+            "module" => {
+                let mut globs = Vec::new();
+                let mut found = None;
+                for child_id in ids(&inner["items"]) {
+                    let Some(child) = doc.item(&child_id) else {
+                        continue;
+                    };
+                    if let Some(import) = child["inner"].get("use") {
+                        if import["is_glob"].as_bool() == Some(true) {
+                            globs.push(import["id"].clone());
+                        } else if import["name"].as_str() == Some(name) {
+                            found = Some(Err(import["id"].clone()));
+                            break;
+                        }
+                    } else if child["name"].as_str() == Some(name) {
+                        found = Some(Ok(child_id));
+                        break;
+                    }
+                }
+                match found {
+                    Some(Ok(child_id)) => Step::Item {
+                        krate: krate.to_string(),
+                        id: child_id,
+                    },
+                    Some(Err(target)) => self.target(krate, &target),
+                    None => {
+                        for glob in globs {
+                            let Step::Item { krate, id } = self.target(krate, &glob) else {
+                                return Step::Unknown;
+                            };
+                            match self.child(&krate, &id, name, depth + 1) {
+                                Step::Missing => continue,
+                                step => return step,
+                            }
+                        }
+                        Step::Missing
+                    }
+                }
+            }
+            "struct" | "enum" | "union" => {
+                let mut trait_name = None;
+                let variants = ids(&inner["variants"]);
+                if variants
+                    .iter()
+                    .any(|v| doc.item(v).and_then(|v| v["name"].as_str()) == Some(name))
+                {
+                    return Step::Assoc { trait_name: None };
+                }
+                for impl_id in ids(&inner["impls"]) {
+                    let Some(block) = doc.item(&impl_id).map(|i| &i["inner"]["impl"]) else {
+                        continue;
+                    };
+                    let provides = ids(&block["items"])
+                        .iter()
+                        .any(|i| doc.item(i).and_then(|i| i["name"].as_str()) == Some(name))
+                        || block["provided_trait_methods"]
+                            .as_array()
+                            .is_some_and(|m| m.iter().any(|m| m.as_str() == Some(name)));
+                    if !provides {
+                        continue;
+                    }
+                    match block["trait"]["path"].as_str() {
+                        None => return Step::Assoc { trait_name: None },
+                        Some(path) => {
+                            let last = path.rsplit("::").next().unwrap_or(path);
+                            trait_name.get_or_insert_with(|| last.to_string());
+                        }
+                    }
+                }
+                match trait_name {
+                    Some(trait_name) => Step::Assoc {
+                        trait_name: Some(trait_name),
+                    },
+                    None => Step::Missing,
+                }
+            }
+            "trait" => {
+                let declared = ids(&inner["items"])
+                    .iter()
+                    .any(|i| doc.item(i).and_then(|i| i["name"].as_str()) == Some(name));
+                if declared {
+                    Step::Assoc { trait_name: None }
+                } else {
+                    Step::Missing
+                }
+            }
+            _ => Step::Unknown,
+        }
+    }
+
+    /// Follows a `use` target, which may live in another crate.
+    fn target(&mut self, krate: &str, target: &Value) -> Step {
+        let Some(id) = id_key(target) else {
+            return Step::Unknown;
+        };
+        let Some(doc) = self.load(krate) else {
+            return Step::Unknown;
+        };
+        if doc.item(&id).is_some() {
+            return Step::Item {
+                krate: krate.to_string(),
+                id,
+            };
+        }
+        let path: Vec<String> = doc.doc["paths"][&id]["path"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|s| s.as_str().map(str::to_string))
+            .collect();
+        let Some(other) = path.first().cloned() else {
+            return Step::Unknown;
+        };
+        match self
+            .load(&other)
+            .and_then(|doc| doc.own_paths.get(&path).cloned())
+        {
+            Some(id) => Step::Item { krate: other, id },
+            None => Step::Unknown,
+        }
+    }
+
+    fn load(&mut self, krate: &str) -> Option<&CrateDoc> {
+        if !self.crates.contains_key(krate) {
+            let doc = self.read_or_generate(krate).map(CrateDoc::new);
+            self.crates.insert(krate.to_string(), doc);
+        }
+        self.crates.get(krate).and_then(Option::as_ref)
+    }
+
+    fn read_or_generate(&self, krate: &str) -> Option<Value> {
+        let file = self.target_dir.join("doc").join(format!("{}.json", krate));
+        if !file.exists() {
+            let package = self.package.as_deref()?;
+            generate(package, &self.target_dir, krate);
+        }
+        let text = fs::read_to_string(&file).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+}
+
+/// Runs nightly rustdoc for `krate` inside `package`. Failures leave no file
+/// behind, which the caller treats as unavailable docs.
+fn generate(package: &Path, target_dir: &Path, krate: &str) {
+    // Crate names use `_`; package names usually use `-`.
+    for spec in [krate.replace('_', "-"), krate.to_string()] {
+        let status = Command::new("cargo")
+            .args([
+                "+nightly",
+                "rustdoc",
+                "--offline",
+                "--quiet",
+                "--manifest-path",
+            ])
+            .arg(package.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(target_dir)
+            .args(["-p", &spec, "--", "-Z", "unstable-options"])
+            .args(["--output-format", "json"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if status.is_ok_and(|s| s.success()) {
+            return;
+        }
+    }
+}
+
+/// Ids are integers in recent format versions and strings in older ones.
+fn id_key(id: &Value) -> Option<String> {
+    match id {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+fn ids(list: &Value) -> Vec<String> {
+    list.as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(id_key)
+        .collect()
+}
@@ -0,0 +1,527 @@
+//! Splitting of multi-program sample files into standalone snippets.
+//!
+//! Files such as `o1-rust-full.rs` are a concatenation of independent
+//! programs, several of them with their own `fn main`. A snippet starts at a
+//! `/* ==== Title ==== */` banner or, where there is no banner, at the first
+//! top-level `use`, entry point or unrelated item that follows an already
+//! complete program.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::{Attribute, Item};
+
+use crate::error::{Error, Result};
+
+/// How an extracted snippet is meant to be built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetKind {
+    /// A program with a `main`, `#[tokio::main]` or `#[launch]` entry point.
+    Binary,
+    /// A library, such as an Anchor `#[program]` module.
+    Library,
+    /// A file holding only `#[test]`-style functions.
+    Test,
+}
+
+/// The `/* ==== Title ==== ... */` block comment that opens a snippet.
+#[derive(Debug, Clone)]
+pub struct Banner {
+    pub title: String,
+    /// Everything between the closing rule and the `*/` line.
+    pub body: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// One self-contained program cut out of a sample file.
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    /// Stable identifier, `<file stem>/<index>-<slug>`.
+    pub id: String,
+    pub source: PathBuf,
+    /// 1-based position of the snippet within its source file.
+    pub index: usize,
+    pub slug: String,
+    pub kind: SnippetKind,
+    pub banner: Option<Banner>,
+    /// First line of the snippet (1-based, inclusive).
+    pub start_line: usize,
+    /// Last line of the snippet (1-based, inclusive).
+    pub end_line: usize,
+    pub code: String,
+    /// Crates that other snippets of the same file define, such as the
+    /// Anchor program `#[program] pub mod counter`, which this snippet may
+    /// name as if they were dependencies.
+    pub sibling_crates: Vec<String>,
+}
+
+impl Snippet {
+    /// File stem of the sample this snippet was cut from, e.g. `o1-rust-full`.
+    pub fn source_stem(&self) -> String {
+        file_stem(&self.source)
+    }
+
+    /// Directory name used when the snippet is written out as a package.
+    pub fn dir_name(&self) -> String {
+        format!("{:02}-{}", self.index, self.slug)
+    }
+
+    /// A valid Cargo package name for the snippet.
+    pub fn package_name(&self) -> String {
+        format!("{}-{}", self.source_stem(), self.dir_name())
+    }
+}
+
+/// Reads a Rust sample from disk and splits it into snippets.
+pub fn split_file(path: &Path) -> Result<Vec<Snippet>> {
+    let source = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    split_source(path, &source)
+}
+
+/// Splits the Rust `source` of `path` into snippets, in file order.
+pub fn split_source(path: &Path, source: &str) -> Result<Vec<Snippet>> {
+    let file = syn::parse_file(source).map_err(|source| Error::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let lines: Vec<&str> = source.lines().collect();
+    let banners = find_banners(&lines);
+    let items: Vec<ItemInfo> = file.items.iter().map(ItemInfo::new).collect();
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut prev_end = 0;
+    for (i, item) in items.iter().enumerate() {
+        let banner = banners
+            .iter()
+            .position(|b| b.start_line > prev_end && b.end_line < item.start_line);
+        let split = match (groups.last(), banner) {
+            (_, Some(_)) | (None, _) => true,
+            (Some(group), None) => {
+                group.has_entry
+                    && (item.is_import
+                        || item.is_entry
+                        || (!group.references(item) && entry_ahead(&items[i..], &banners)))
+            }
+        };
+        if split {
+            groups.push(Group::new(banner));
+        }
+        groups
+            .last_mut()
+            .expect("a group was pushed above")
+            .push(i, item);
+        prev_end = item.end_line;
+    }
+
+    let stem = file_stem(path);
+    let programs: Vec<Vec<String>> = groups
+        .iter()
+        .map(|group| {
+            group
+                .items
+                .iter()
+                .filter(|&&i| items[i].is_program)
+                .filter_map(|&i| items[i].name.clone())
+                .collect()
+        })
+        .collect();
+    let snippets = groups
+        .into_iter()
+        .enumerate()
+        .map(|(n, group)| {
+            let banner = group.banner.map(|b| banners[b].clone());
+            let first = &items[group.items[0]];
+            let last = &items[*group.items.last().expect("groups are never empty")];
+            let start_line = match &banner {
+                Some(b) => b.start_line,
+                // A file header comment belongs to the first program even
+                // when a blank line separates it from the code.
+                None if n == 0 => lines
+                    .iter()
+                    .position(|l| !l.trim().is_empty())
+                    .map_or(first.start_line, |i| i + 1),
+                None => leading_comment_start(&lines, first.start_line),
+            };
+            let end_line = last.end_line;
+            let index = n + 1;
+            let slug = match &banner {
+                Some(b) => slugify(strip_numbering(&b.title)),
+                None => group
+                    .items
+                    .iter()
+                    .filter_map(|&i| items[i].name.as_deref())
+                    .find(|name| *name != "main")
+                    .map(slugify)
+                    .unwrap_or_else(|| "main".to_string()),
+            };
+            let kind = if group.items.iter().any(|&i| items[i].is_entry) {
+                SnippetKind::Binary
+            } else if group.items.iter().any(|&i| items[i].is_test) {
+                SnippetKind::Test
+            } else {
+                SnippetKind::Library
+            };
+            let mut code = lines[start_line - 1..end_line].join("\n");
+            code.push('\n');
+            Snippet {
+                id: format!("{}/{:02}-{}", stem, index, slug),
+                source: path.to_path_buf(),
+                index,
+                slug,
+                kind,
+                banner,
+                start_line,
+                end_line,
+                code,
+                sibling_crates: programs
+                    .iter()
+                    .enumerate()
+                    .filter(|&(other, _)| other != n)
+                    .flat_map(|(_, names)| names.iter().cloned())
+                    .collect(),
+            }
+        })
+        .collect();
+    Ok(snippets)
+}
+
+/// Finds every `/*` block whose next line is a `====` rule.
+pub fn find_banners(lines: &[&str]) -> Vec<Banner> {
+    let mut banners = Vec::new();
+    let mut i = 0;
+    while i + 1 < lines.len() {
+        if lines[i].trim() != "/*" || !is_rule(lines[i + 1]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut j = i + 2;
+        let mut title = Vec::new();
+        while j < lines.len() && !is_rule(lines[j]) && lines[j].trim() != "*/" {
+            title.push(lines[j].trim());
+            j += 1;
+        }
+        if j < lines.len() && is_rule(lines[j]) {
+            j += 1;
+        }
+        let body_start = j;
+        while j < lines.len() && lines[j].trim() != "*/" {
+            j += 1;
+        }
+        let body_end = j.min(lines.len());
+        banners.push(Banner {
+            title: title.join(" "),
+            body: lines[body_start..body_end].join("\n"),
+            start_line: start + 1,
+            end_line: body_end + 1,
+        });
+        i = body_end + 1;
+    }
+    banners
+}
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 5 && line.chars().all(|c| c == '=')
+}
+
+/// Top-level item facts the splitter needs.
+struct ItemInfo {
+    start_line: usize,
+    end_line: usize,
+    name: Option<String>,
+    is_import: bool,
+    is_entry: bool,
+    is_test: bool,
+    /// An Anchor `#[program]` module, which builds into a crate of its name.
+    is_program: bool,
+    idents: HashSet<String>,
+}
+
+impl ItemInfo {
+    fn new(item: &Item) -> Self {
+        let span = item.span();
+        let start_line = attrs(item)
+            .iter()
+            .map(|a| a.span().start().line)
+            .fold(span.start().line, usize::min);
+        let (is_entry, is_test) = match item {
+            Item::Fn(f) => (
+                f.sig.ident == "main"
+                    || f.attrs
+                        .iter()
+                        .any(|a| has_last_segment(a, &["main", "launch"])),
+                f.attrs.iter().any(|a| has_last_segment(a, &["test"])),
+            ),
+            _ => (false, false),
+        };
+        let is_program = match item {
+            Item::Mod(m) => m.attrs.iter().any(|a| has_last_segment(a, &["program"])),
+            _ => false,
+        };
+        let mut idents = HashSet::new();
+        collect_idents(item.to_token_stream(), &mut idents);
+        ItemInfo {
+            start_line,
+            end_line: span.end().line,
+            name: item_name(item),
+            is_import: matches!(item, Item::Use(_) | Item::ExternCrate(_)),
+            is_entry,
+            is_test,
+            is_program,
+            idents,
+        }
+    }
+}
+
+/// Items accumulated into one snippet so far.
+struct Group {
+    banner: Option<usize>,
+    items: Vec<usize>,
+    has_entry: bool,
+    idents: HashSet<String>,
+}
+
+impl Group {
+    fn new(banner: Option<usize>) -> Self {
+        Group {
+            banner,
+            items: Vec::new(),
+            has_entry: false,
+            idents: HashSet::new(),
+        }
+    }
+
+    fn push(&mut self, index: usize, item: &ItemInfo) {
+        self.items.push(index);
+        self.has_entry |= item.is_entry;
+        self.idents.extend(item.idents.iter().cloned());
+    }
+
+    /// Whether anything already in the group names `item`, i.e. it is a helper
+    /// defined after the code that uses it.
+    fn references(&self, item: &ItemInfo) -> bool {
+        match &item.name {
+            Some(name) => self.idents.contains(name),
+            None => true,
+        }
+    }
+}
+
+/// Whether another entry point follows before the next banner, so an
+/// unreferenced item belongs to that next program rather than this one.
+fn entry_ahead(rest: &[ItemInfo], banners: &[Banner]) -> bool {
+    let from = rest[0].start_line;
+    match rest.iter().find(|item| item.is_entry) {
+        Some(entry) => !banners
+            .iter()
+            .any(|b| b.start_line > from && b.end_line < entry.start_line),
+        None => false,
+    }
+}
+
+fn attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(i) => &i.attrs,
+        Item::Enum(i) => &i.attrs,
+        Item::ExternCrate(i) => &i.attrs,
+        Item::Fn(i) => &i.attrs,
+        Item::ForeignMod(i) => &i.attrs,
+        Item::Impl(i) => &i.attrs,
+        Item::Macro(i) => &i.attrs,
+        Item::Mod(i) => &i.attrs,
+        Item::Static(i) => &i.attrs,
+        Item::Struct(i) => &i.attrs,
+        Item::Trait(i) => &i.attrs,
+        Item::TraitAlias(i) => &i.attrs,
+        Item::Type(i) => &i.attrs,
+        Item::Union(i) => &i.attrs,
+        Item::Use(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    let ident = match item {
+        Item::Const(i) => &i.ident,
+        Item::Enum(i) => &i.ident,
+        Item::Fn(i) => &i.sig.ident,
+        Item::Macro(i) => i.ident.as_ref()?,
+        Item::Mod(i) => &i.ident,
+        Item::Static(i) => &i.ident,
+        Item::Struct(i) => &i.ident,
+        Item::Trait(i) => &i.ident,
+        Item::Type(i) => &i.ident,
+        Item::Union(i) => &i.ident,
+        Item::Impl(i) => match &*i.self_ty {
+            syn::Type::Path(p) => &p.path.segments.last()?.ident,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(ident.to_string())
+}
+
+fn has_last_segment(attr: &Attribute, names: &[&str]) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|s| names.iter().any(|n| s.ident == n))
+}
+
+fn collect_idents(tokens: TokenStream, out: &mut HashSet<String>) {
+    for tree in tokens {
+        match tree {
+            TokenTree::Ident(ident) => {
+                out.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), out),
+            _ => {}
+        }
+    }
+}
+
+/// Walks up from `line` over directly preceding `//` comment lines.
+fn leading_comment_start(lines: &[&str], line: usize) -> usize {
+    let mut start = line;
+    while start > 1 && lines[start - 2].trim_start().starts_with("//") {
+        start -= 1;
+    }
+    start
+}
+
+/// Drops list numbering such as `1) ` from a banner title.
+fn strip_numbering(title: &str) -> &str {
+    let rest = title.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == title.len() {
+        return title;
+    }
+    rest.strip_prefix(')').map(str::trim_start).unwrap_or(title)
+}
+
+/// Lowercase ASCII slug; Turkish letters are folded to their ASCII base.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        let c = match c {
+            'ı' | 'İ' => 'i',
+            'ş' | 'Ş' => 's',
+            'ğ' | 'Ğ' => 'g',
+            'ü' | 'Ü' => 'u',
+            'ö' | 'Ö' => 'o',
+            'ç' | 'Ç' => 'c',
+            c => c.to_ascii_lowercase(),
+        };
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("snippet");
+    }
+    slug
+}
+
+pub(crate) fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(name)
+    }
+
+    fn split(source: &str) -> Vec<Snippet> {
+        split_source(Path::new("fixture.rs"), source).unwrap()
+    }
+
+    #[test]
+    fn samples_split_into_their_programs() {
+        let full = split_file(&sample("o1-rust-full.rs")).unwrap();
+        assert_eq!(full.len(), 27);
+        assert_eq!(full[0].id, "o1-rust-full/01-factorial-example-simple");
+        assert_eq!(
+            full[4].id,
+            "o1-rust-full/05-concurrency-example-multi-threaded-sum"
+        );
+        assert!(full.iter().all(|s| s.kind == SnippetKind::Binary));
+
+        let solana = split_file(&sample("o1-rust-solana.rs")).unwrap();
+        assert_eq!(solana.len(), 12);
+        assert_eq!(solana[0].kind, SnippetKind::Library);
+        assert_eq!(solana[11].kind, SnippetKind::Test);
+        // Snippets cover the file in order without overlapping.
+        for pair in solana.windows(2) {
+            assert!(pair[0].end_line < pair[1].start_line);
+        }
+    }
+
+    #[test]
+    fn banners_start_snippets() {
+        let source = "/*\n=====\n1) Hello Dünya\n=====\nPurpose: greet\n*/\n\
+                      fn main() {\n    println!(\"hi\");\n}\n\
+                      /*\n=====\nSecond\n=====\n*/\n\
+                      fn helper() {}\n";
+        let snippets = split(source);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].slug, "hello-dunya");
+        assert_eq!(snippets[0].banner.as_ref().unwrap().body, "Purpose: greet");
+        assert_eq!((snippets[0].start_line, snippets[0].end_line), (1, 9));
+        assert_eq!(snippets[1].kind, SnippetKind::Library);
+        assert_eq!(snippets[1].id, "fixture/02-second");
+    }
+
+    #[test]
+    fn unbannered_programs_split_at_imports_and_entry_points() {
+        let source = "use std::fmt;\nfn main() { helper(); }\nfn helper() {}\n\
+                      // Second program\nuse std::io;\nfn main() {}\n\
+                      fn main() {}\n";
+        let snippets = split(source);
+        let spans: Vec<(usize, usize)> = snippets
+            .iter()
+            .map(|s| (s.start_line, s.end_line))
+            .collect();
+        // The helper defined after `main` stays with it, and the comment
+        // above the second `use` opens the second program.
+        assert_eq!(spans, [(1, 3), (4, 6), (7, 7)]);
+        assert_eq!(snippets[0].slug, "helper");
+        assert_eq!(snippets[2].slug, "main");
+    }
+
+    #[test]
+    fn anchor_programs_are_sibling_crates() {
+        let source = "/*\n=====\nProgram\n=====\n*/\n#[program]\npub mod counter {}\n\
+                      /*\n=====\nClient\n=====\n*/\nuse counter::Counter;\nfn main() {}\n";
+        let snippets = split(source);
+        assert_eq!(snippets.len(), 2);
+        assert!(snippets[0].sibling_crates.is_empty());
+        assert_eq!(snippets[1].sibling_crates, ["counter"]);
+    }
+
+    #[test]
+    fn slugs_fold_turkish_letters() {
+        assert_eq!(
+            slugify("Trait ve Generics ile Özel Stack"),
+            "trait-ve-generics-ile-ozel-stack"
+        );
+        assert_eq!(slugify("Şık, çğı!"), "sik-cgi");
+        assert_eq!(slugify("???"), "snippet");
+        assert_eq!(strip_numbering("12) Merge Sort"), "Merge Sort");
+        assert_eq!(strip_numbering("2024 plans"), "2024 plans");
+    }
+}
@@ -0,0 +1,422 @@
+//! Stylometric features for telling generated Rust apart from hand-written
+//! Rust.
+//!
+//! Every feature is computed from one snippet on its own, so the vectors can
+//! be fed straight to a detector. Comment features come from
+//! [`comments::extract`], everything else from the `syn` tree, except the
+//! `.unwrap()`/`.expect()` counts, which walk raw tokens so calls inside
+//! `println!` and other macros are seen too. The banner is reported as a
+//! feature of its own and left out of the comment counts.
+
+use std::collections::BTreeSet;
+
+use proc_macro2::{TokenStream, TokenTree};
+use serde::Serialize;
+use syn::visit::{self, Visit};
+
+use crate::comments::{self, CommentKind};
+use crate::manifest::kind_name;
+use crate::snippet::Snippet;
+
+/// The feature vector of one snippet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Features {
+    pub id: String,
+    pub kind: &'static str,
+    /// Whether `syn` parsed the snippet; syntax features are zero otherwise.
+    pub parsed: bool,
+    pub has_banner: bool,
+    pub banner_lines: usize,
+    /// Lines outside the banner.
+    pub lines: usize,
+    pub code_lines: usize,
+    /// Lines holding nothing but comment text.
+    pub comment_lines: usize,
+    pub blank_lines: usize,
+    pub comment_to_code_ratio: f64,
+    pub doc_comment_lines: usize,
+    /// Doc comment lines per code line.
+    pub doc_comment_density: f64,
+    /// Share of functions, types, traits, consts and modules with a doc
+    /// comment.
+    pub documented_item_ratio: f64,
+    /// Comments after code on the same line.
+    pub trailing_comments: usize,
+    /// Comments numbered like `// 1) Start a local program test environment`.
+    pub step_comments: usize,
+    /// Share of ordinary comments that are numbered steps.
+    pub step_comment_ratio: f64,
+    pub unwrap_calls: usize,
+    pub expect_calls: usize,
+    /// `.unwrap()` plus `.expect()` calls per 100 code lines.
+    pub unwrap_expect_per_100_lines: f64,
+    /// `?` operators per 100 code lines.
+    pub try_operators_per_100_lines: f64,
+    /// Distinct names the snippet declares: items, fields, variants,
+    /// parameters and bindings.
+    pub identifiers: usize,
+    pub snake_case_ratio: f64,
+    pub upper_camel_case_ratio: f64,
+    pub screaming_snake_case_ratio: f64,
+    pub single_letter_ratio: f64,
+    pub mean_identifier_length: f64,
+}
+
+/// Computes the features of `snippet`.
+pub fn extract(snippet: &Snippet) -> Features {
+    let lines: Vec<&str> = snippet.code.lines().collect();
+    let banner = snippet.banner.as_ref().map(|b| {
+        (
+            b.start_line - snippet.start_line + 1,
+            b.end_line - snippet.start_line + 1,
+        )
+    });
+    let in_banner = |line: usize| banner.is_some_and(|(start, end)| (start..=end).contains(&line));
+
+    let mut comment_only = BTreeSet::new();
+    let mut doc_comment_lines = 0;
+    let mut trailing_comments = 0;
+    let mut ordinary_comments = 0;
+    let mut step_comments = 0;
+    for comment in comments::extract(&snippet.code) {
+        if in_banner(comment.line) {
+            continue;
+        }
+        if comment.trailing {
+            trailing_comments += 1;
+        } else {
+            comment_only.extend(comment.line..=comment.end_line);
+        }
+        if comment.kind.is_doc() {
+            doc_comment_lines += comment.end_line - comment.line + 1;
+        } else if comment.kind == CommentKind::Line {
+            ordinary_comments += 1;
+            if is_step(&comment.text) {
+                step_comments += 1;
+            }
+        }
+    }
+
+    let banner_lines = banner.map_or(0, |(start, end)| end - start + 1);
+    let mut blank_lines = 0;
+    let mut comment_lines = 0;
+    for (n, line) in lines.iter().enumerate() {
+        if in_banner(n + 1) {
+            continue;
+        }
+        if line.trim().is_empty() {
+            blank_lines += 1;
+        } else if comment_only.contains(&(n + 1)) {
+            comment_lines += 1;
+        }
+    }
+    let total = lines.len() - banner_lines;
+    let code_lines = total - blank_lines - comment_lines;
+
+    let mut syntax = SyntaxCollector::default();
+    let parsed = match syn::parse_file(&snippet.code) {
+        Ok(file) => {
+            syntax.visit_file(&file);
+            true
+        }
+        Err(_) => false,
+    };
+    let (unwrap_calls, expect_calls) = match snippet.code.parse::<TokenStream>() {
+        Ok(tokens) => count_calls(tokens),
+        Err(_) => (0, 0),
+    };
+
+    let names = &syntax.identifiers;
+    let count_style = |style: NameStyle| names.iter().filter(|n| name_style(n) == style).count();
+    let mean_length = if names.is_empty() {
+        0.0
+    } else {
+        names.iter().map(|n| n.chars().count()).sum::<usize>() as f64 / names.len() as f64
+    };
+
+    Features {
+        id: snippet.id.clone(),
+        kind: kind_name(snippet.kind),
+        parsed,
+        has_banner: banner.is_some(),
+        banner_lines,
+        lines: total,
+        code_lines,
+        comment_lines,
+        blank_lines,
+        comment_to_code_ratio: ratio(comment_lines, code_lines),
+        doc_comment_lines,
+        doc_comment_density: ratio(doc_comment_lines, code_lines),
+        documented_item_ratio: ratio(syntax.documented_items, syntax.items),
+        trailing_comments,
+        step_comments,
+        step_comment_ratio: ratio(step_comments, ordinary_comments),
+        unwrap_calls,
+        expect_calls,
+        unwrap_expect_per_100_lines: ratio(100 * (unwrap_calls + expect_calls), code_lines),
+        try_operators_per_100_lines: ratio(100 * syntax.try_operators, code_lines),
+        identifiers: names.len(),
+        snake_case_ratio: ratio(count_style(NameStyle::Snake), names.len()),
+        upper_camel_case_ratio: ratio(count_style(NameStyle::UpperCamel), names.len()),
+        screaming_snake_case_ratio: ratio(count_style(NameStyle::ScreamingSnake), names.len()),
+        single_letter_ratio: ratio(
+            names.iter().filter(|n| n.chars().count() == 1).count(),
+            names.len(),
+        ),
+        mean_identifier_length: round(mean_length),
+    }
+}
+
+/// Whether a comment reads as a numbered step: `1) ...`, `2. ...`,
+/// `Step 3: ...` or the Turkish `Adım 3: ...`.
+fn is_step(text: &str) -> bool {
+    let text = text
+        .strip_prefix("Step ")
+        .or_else(|| text.strip_prefix("Adım "))
+        .unwrap_or(text);
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return false;
+    }
+    let mut rest = text[digits..].chars();
+    matches!(rest.next(), Some(')' | '.' | ':')) && rest.next().is_none_or(char::is_whitespace)
+}
+
+/// Counts `.unwrap()` and `.expect(...)` calls, descending into macro input.
+fn count_calls(tokens: TokenStream) -> (usize, usize) {
+    let (mut unwraps, mut expects) = (0, 0);
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            TokenTree::Group(group) => {
+                let (u, e) = count_calls(group.stream());
+                unwraps += u;
+                expects += e;
+            }
+            TokenTree::Ident(ident) => {
+                let after_dot =
+                    i > 0 && matches!(&tokens[i - 1], TokenTree::Punct(p) if p.as_char() == '.');
+                let called = matches!(
+                    tokens.get(i + 1),
+                    Some(TokenTree::Group(g)) if g.delimiter() == proc_macro2::Delimiter::Parenthesis
+                );
+                if after_dot && called {
+                    if ident == "unwrap" {
+                        unwraps += 1;
+                    } else if ident == "expect" {
+                        expects += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    (unwraps, expects)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NameStyle {
+    Snake,
+    UpperCamel,
+    ScreamingSnake,
+    Other,
+}
+
+fn name_style(name: &str) -> NameStyle {
+    let name = name.trim_start_matches('_');
+    let Some(first) = name.chars().next() else {
+        return NameStyle::Other;
+    };
+    let has_lower = name.chars().any(|c| c.is_lowercase());
+    let has_upper = name.chars().any(|c| c.is_uppercase());
+    if first.is_lowercase() && !has_upper {
+        NameStyle::Snake
+    } else if first.is_uppercase() && !has_lower && name.chars().count() > 1 {
+        NameStyle::ScreamingSnake
+    } else if first.is_uppercase() && !name.contains('_') {
+        NameStyle::UpperCamel
+    } else {
+        NameStyle::Other
+    }
+}
+
+/// Collects declared names, documentable items and `?` operators.
+#[derive(Default)]
+struct SyntaxCollector {
+    identifiers: BTreeSet<String>,
+    items: usize,
+    documented_items: usize,
+    try_operators: usize,
+}
+
+impl SyntaxCollector {
+    fn declare(&mut self, ident: &syn::Ident) {
+        self.identifiers.insert(ident.to_string());
+    }
+
+    fn documentable(&mut self, attrs: &[syn::Attribute]) {
+        self.items += 1;
+        if attrs.iter().any(|a| a.path().is_ident("doc")) {
+            self.documented_items += 1;
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for SyntaxCollector {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        use syn::Item;
+        let declared = match item {
+            Item::Const(i) => Some((&i.attrs, &i.ident)),
+            Item::Enum(i) => Some((&i.attrs, &i.ident)),
+            Item::Fn(i) => Some((&i.attrs, &i.sig.ident)),
+            Item::Mod(i) => Some((&i.attrs, &i.ident)),
+            Item::Static(i) => Some((&i.attrs, &i.ident)),
+            Item::Struct(i) => Some((&i.attrs, &i.ident)),
+            Item::Trait(i) => Some((&i.attrs, &i.ident)),
+            Item::Type(i) => Some((&i.attrs, &i.ident)),
+            Item::Union(i) => Some((&i.attrs, &i.ident)),
+            _ => None,
+        };
+        if let Some((attrs, ident)) = declared {
+            self.documentable(attrs);
+            self.declare(ident);
+        }
+        visit::visit_item(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, f: &'ast syn::ImplItemFn) {
+        self.documentable(&f.attrs);
+        self.declare(&f.sig.ident);
+        visit::visit_impl_item_fn(self, f);
+    }
+
+    fn visit_trait_item_fn(&mut self, f: &'ast syn::TraitItemFn) {
+        self.documentable(&f.attrs);
+        self.declare(&f.sig.ident);
+        visit::visit_trait_item_fn(self, f);
+    }
+
+    fn visit_field(&mut self, field: &'ast syn::Field) {
+        if let Some(ident) = &field.ident {
+            self.declare(ident);
+        }
+        visit::visit_field(self, field);
+    }
+
+    fn visit_variant(&mut self, variant: &'ast syn::Variant) {
+        self.declare(&variant.ident);
+        visit::visit_variant(self, variant);
+    }
+
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.declare(&pat.ident);
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_expr_try(&mut self, expr: &'ast syn::ExprTry) {
+        self.try_operators += 1;
+        visit::visit_expr_try(self, expr);
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    round(part as f64 / whole as f64)
+}
+
+fn round(x: f64) -> f64 {
+    (x * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::snippet::{split_file, split_source};
+
+    const FIXTURE: &str = "/*
+=====
+Demo
+=====
+Purpose:
+    Show every feature once.
+*/
+
+/// Adds one.
+fn add_one(x: i32) -> i32 {
+    // 1) Add.
+    x + 1 // done
+}
+
+fn main() {
+    let value: i32 = \"4\".parse().unwrap();
+    println!(\"{}\", add_one(value).checked_add(1).expect(\"overflow\"));
+}
+";
+
+    #[test]
+    fn counts_lines_comments_and_calls() {
+        let snippets = split_source(Path::new("fixture.rs"), FIXTURE).unwrap();
+        let features = extract(&snippets[0]);
+        assert!(features.parsed && features.has_banner);
+        assert_eq!(features.banner_lines, 7);
+        assert_eq!(
+            (
+                features.lines,
+                features.code_lines,
+                features.comment_lines,
+                features.blank_lines
+            ),
+            (11, 7, 2, 2)
+        );
+        assert_eq!(features.comment_to_code_ratio, 0.286);
+        assert_eq!(features.doc_comment_lines, 1);
+        assert_eq!(features.documented_item_ratio, 0.5);
+        assert_eq!(features.trailing_comments, 1);
+        assert_eq!(features.step_comments, 1);
+        assert_eq!(features.step_comment_ratio, 0.5);
+        // The `.expect()` inside `println!` counts too.
+        assert_eq!((features.unwrap_calls, features.expect_calls), (1, 1));
+        assert_eq!(features.unwrap_expect_per_100_lines, 28.571);
+        assert_eq!(features.try_operators_per_100_lines, 0.0);
+    }
+
+    #[test]
+    fn classifies_identifier_styles() {
+        let snippets = split_source(Path::new("fixture.rs"), FIXTURE).unwrap();
+        let features = extract(&snippets[0]);
+        // add_one, x, main and value.
+        assert_eq!(features.identifiers, 4);
+        assert_eq!(features.snake_case_ratio, 1.0);
+        assert_eq!(features.single_letter_ratio, 0.25);
+        assert_eq!(features.mean_identifier_length, 4.25);
+
+        assert_eq!(name_style("MAX_LEN"), NameStyle::ScreamingSnake);
+        assert_eq!(name_style("HashMap"), NameStyle::UpperCamel);
+        assert_eq!(name_style("_unused"), NameStyle::Snake);
+        assert_eq!(name_style("T"), NameStyle::UpperCamel);
+        assert_eq!(name_style("Mixed_Case"), NameStyle::Other);
+    }
+
+    #[test]
+    fn recognises_numbered_steps() {
+        assert!(is_step("1) Start a local program test environment"));
+        assert!(is_step("2. Send the transaction"));
+        assert!(is_step("Step 3: check the balance"));
+        assert!(is_step("Adım 4: sonucu yazdır"));
+        assert!(!is_step("3.14 is pi"));
+        assert!(!is_step("10 items"));
+    }
+
+    #[test]
+    fn unbannered_sample_snippets_report_no_banner() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../o1-rust-full.rs");
+        let features: Vec<Features> = split_file(&path).unwrap().iter().map(extract).collect();
+        assert!(features.iter().all(|f| f.parsed));
+        assert_eq!(features.iter().filter(|f| f.has_banner).count(), 16);
+        assert!(features[16..].iter().all(|f| f.banner_lines == 0));
+    }
+}
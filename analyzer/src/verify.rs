@@ -0,0 +1,494 @@
+//! Building and running snippets to check their claimed `Example Output`.
+//!
+//! Each binary snippet is written out as a package, built offline against a
+//! shared target directory, and run in a fresh directory that holds only the
+//! fixture files. On Linux the program is started in an empty network
+//! namespace (`unshare -rn`) when that is permitted, so samples that reach for
+//! the network fail fast instead of depending on the host.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::emit;
+use crate::error::{Error, Result};
+use crate::metadata;
+use crate::snippet::{Snippet, SnippetKind};
+
+/// Files every run directory starts with, matching what the banners ask for.
+const BUILTIN_FIXTURES: &[(&str, &str)] = &[
+    ("input.txt", "Hello, world!\n"),
+    ("data.csv", "name,score\nAlice,50\nBob,75\nCharlie,90\n"),
+    ("data.json", "{\n  \"name\": \"Alice\",\n  \"age\": 30\n}\n"),
+    ("data.txt", "first line\nsecond line\nthird line\n"),
+];
+
+/// Name of the per-snippet fixture file that is piped to stdin.
+const STDIN_FIXTURE: &str = "stdin";
+
+/// Outcome of verifying one snippet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Every claimed output line was printed.
+    Match,
+    /// Some, but not all, claimed lines were printed.
+    Partial,
+    /// None of the claimed lines were printed.
+    Mismatch,
+    /// The program ran but its banner claims no concrete output.
+    NoClaim,
+    /// The program ran but its banner only describes the output in prose,
+    /// like `Sıralanmış dizi ekrana yazılır.` ("the sorted array is printed").
+    Descriptive,
+    /// The package did not compile.
+    BuildFailed,
+    /// The program exited unsuccessfully or panicked.
+    RunFailed,
+    /// The program was still running when the time limit expired.
+    Timeout,
+    /// Libraries and test-only snippets have nothing to run.
+    NotRunnable,
+}
+
+/// The verification record for one snippet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub id: String,
+    pub status: Status,
+    /// Claimed output lines that were found, in order, in stdout.
+    pub matched_lines: usize,
+    /// Claimed output lines that could be checked.
+    pub expected_lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    /// The last lines of compiler or program stderr, for failures.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+/// Where and how snippets are built and run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Receives the generated packages, their shared `target/` and run dirs.
+    pub work_dir: PathBuf,
+    /// Optional directory whose files replace or extend the built-in
+    /// fixtures. A `<file stem>/<index>-<slug>/` subdirectory applies to one
+    /// snippet only; a `stdin` file in either place is piped to the program.
+    pub fixtures: Option<PathBuf>,
+    pub timeout: Duration,
+}
+
+/// Builds, runs and checks `snippet`.
+pub fn verify(snippet: &Snippet, options: &Options) -> Result<Verdict> {
+    let mut verdict = Verdict {
+        id: snippet.id.clone(),
+        status: Status::NotRunnable,
+        matched_lines: 0,
+        expected_lines: 0,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+    if snippet.kind != SnippetKind::Binary {
+        return Ok(verdict);
+    }
+
+    let packages = options.work_dir.join("packages");
+    let package = emit::write_package(&packages, snippet)?;
+    let target = options.work_dir.join("target");
+    let build = Command::new("cargo")
+        .args(["build", "--offline", "--quiet", "--manifest-path"])
+        .arg(package.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target)
+        .output()
+        .map_err(|e| Error::io("cargo", e))?;
+    if !build.status.success() {
+        verdict.status = Status::BuildFailed;
+        verdict.stderr = tail(&String::from_utf8_lossy(&build.stderr), 20);
+        return Ok(verdict);
+    }
+
+    let run_dir = options
+        .work_dir
+        .join("runs")
+        .join(snippet.source_stem())
+        .join(snippet.dir_name());
+    let stdin = prepare_run_dir(&run_dir, snippet, options.fixtures.as_deref())?;
+    // The program runs inside `run_dir`, so a relative work dir would not
+    // lead back to it.
+    let binary = target.join("debug").join(snippet.package_name());
+    let binary = std::path::absolute(&binary).map_err(|e| Error::io(&binary, e))?;
+    let run = run_sandboxed(&binary, &run_dir, &stdin, options.timeout)?;
+    verdict.stdout = run.stdout;
+    verdict.exit_code = run.exit_code;
+
+    let expected = snippet
+        .banner
+        .as_ref()
+        .and_then(|b| metadata::parse(b).expected_output);
+    let comparison = compare(
+        expected.as_deref().unwrap_or_default(),
+        &verdict.stdout,
+        &String::from_utf8_lossy(&stdin),
+    );
+    verdict.matched_lines = comparison.matched;
+    verdict.expected_lines = comparison.total;
+    verdict.status = if run.timed_out {
+        Status::Timeout
+    } else if run.exit_code != Some(0) {
+        verdict.stderr = tail(&run.stderr, 20);
+        Status::RunFailed
+    } else {
+        comparison.status()
+    };
+    Ok(verdict)
+}
+
+/// How much of a claimed output appeared in the real output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub matched: usize,
+    pub total: usize,
+    /// The claim had lines, but all of them described the output in prose.
+    pub descriptive: bool,
+}
+
+impl Comparison {
+    pub fn status(&self) -> Status {
+        match (self.matched, self.total) {
+            (_, 0) if self.descriptive => Status::Descriptive,
+            (_, 0) => Status::NoClaim,
+            (m, t) if m == t => Status::Match,
+            (0, _) => Status::Mismatch,
+            _ => Status::Partial,
+        }
+    }
+}
+
+/// Compares the banner's claimed output with the program's stdout.
+///
+/// Lines are compared after collapsing whitespace. Lead-in prose such as
+/// ``If `data.csv` is as above, it might print:`` and trailing remarks like
+/// `4950  (if the numbers 1..=100 are used)` are ignored. A claimed line that
+/// shows typed input after a prompt (`Enter a number: 25`) matches when the
+/// prompt was printed and the rest was fed on stdin. Sentences that describe
+/// the output instead of showing it (see [`is_description`]) are not claims.
+pub fn compare(expected: &str, stdout: &str, stdin: &str) -> Comparison {
+    let (described, claimed): (Vec<String>, Vec<String>) = expected
+        .lines()
+        .filter(|l| !l.trim_end().ends_with(':'))
+        .map(strip_remark)
+        .map(normalize)
+        .filter(|l| !l.is_empty())
+        .partition(|l| is_description(l));
+    let actual: Vec<String> = stdout.lines().map(normalize).collect();
+
+    let mut matched = 0;
+    let mut from = 0;
+    for line in &claimed {
+        let found = actual[from..].iter().position(|a| {
+            a == line
+                || (!a.is_empty()
+                    && line.starts_with(a.as_str())
+                    && stdin.contains(line[a.len()..].trim()))
+        });
+        if let Some(offset) = found {
+            matched += 1;
+            from += offset + 1;
+        }
+    }
+    Comparison {
+        matched,
+        total: claimed.len(),
+        descriptive: claimed.is_empty() && !described.is_empty(),
+    }
+}
+
+/// Participles that follow "is"/"are"/"be" in English descriptions of output.
+const DESCRIBING_VERBS: &[&str] = &[
+    "printed",
+    "displayed",
+    "shown",
+    "written",
+    "saved",
+    "logged",
+    "output",
+    "listed",
+];
+
+/// Endings of Turkish passive present verbs: `yazılır`, `gösterilir`,
+/// `kaydedilir`, `okunur`.
+const TURKISH_PASSIVE_ENDINGS: &[&str] = &[
+    "ılır", "ilir", "ulur", "ülür", "lır", "lir", "nır", "nir", "nur", "nür",
+];
+
+/// Whether a claimed line is a sentence about the output, such as
+/// `The sorted array is printed.` or `Sıralanmış dizi ekrana yazılır.`,
+/// rather than output itself.
+fn is_description(line: &str) -> bool {
+    let Some(sentence) = line.strip_suffix('.') else {
+        return false;
+    };
+    let words: Vec<String> = sentence
+        .split_whitespace()
+        .map(|w| {
+            w.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .collect();
+    let english = words.windows(2).any(|pair| {
+        matches!(pair[0].as_str(), "is" | "are" | "be")
+            && DESCRIBING_VERBS.contains(&pair[1].as_str())
+    });
+    let turkish = words.len() > 1
+        && words.last().is_some_and(|last| {
+            TURKISH_PASSIVE_ENDINGS
+                .iter()
+                .any(|ending| last.ends_with(ending))
+        });
+    english || turkish
+}
+
+fn strip_remark(line: &str) -> &str {
+    match line.find("  (") {
+        Some(at) if line.trim_end().ends_with(')') => &line[..at],
+        _ => line,
+    }
+}
+
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Fills `run_dir` with fixtures and returns the bytes to pipe to stdin.
+fn prepare_run_dir(run_dir: &Path, snippet: &Snippet, fixtures: Option<&Path>) -> Result<Vec<u8>> {
+    if run_dir.exists() {
+        fs::remove_dir_all(run_dir).map_err(|e| Error::io(run_dir, e))?;
+    }
+    fs::create_dir_all(run_dir).map_err(|e| Error::io(run_dir, e))?;
+    for (name, contents) in BUILTIN_FIXTURES {
+        let path = run_dir.join(name);
+        fs::write(&path, contents).map_err(|e| Error::io(path, e))?;
+    }
+    let mut stdin = Vec::new();
+    if let Some(fixtures) = fixtures {
+        let specific = fixtures
+            .join(snippet.source_stem())
+            .join(snippet.dir_name());
+        for dir in [fixtures, specific.as_path()] {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries {
+                let entry = entry.map_err(|e| Error::io(dir, e))?;
+                let path = entry.path();
+                if !path.is_file() {
+                    continue;
+                }
+                if entry.file_name() == STDIN_FIXTURE {
+                    stdin = fs::read(&path).map_err(|e| Error::io(&path, e))?;
+                } else {
+                    let dest = run_dir.join(entry.file_name());
+                    fs::copy(&path, &dest).map_err(|e| Error::io(&dest, e))?;
+                }
+            }
+        }
+    }
+    Ok(stdin)
+}
+
+struct RunOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+    timed_out: bool,
+}
+
+/// Runs `binary` in `dir` with a scrubbed environment, no network where the
+/// platform allows it, and a hard time limit.
+fn run_sandboxed(binary: &Path, dir: &Path, stdin: &[u8], timeout: Duration) -> Result<RunOutput> {
+    let mut command = if network_namespace_available() {
+        let mut command = Command::new("unshare");
+        command.args(["--map-root-user", "--net"]).arg(binary);
+        command
+    } else {
+        Command::new(binary)
+    };
+    let mut child = command
+        .current_dir(dir)
+        .env_clear()
+        .env("PATH", "/usr/bin:/bin")
+        .env("HOME", dir)
+        // Without a network namespace, unroutable proxies keep HTTP clients
+        // from reaching the internet.
+        .env("http_proxy", "http://127.0.0.1:9")
+        .env("https_proxy", "http://127.0.0.1:9")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::io(binary, e))?;
+
+    let mut input = child.stdin.take().expect("stdin is piped");
+    let stdin = stdin.to_vec();
+    // Programs that never read stdin close the pipe early; that is fine.
+    let writer = thread::spawn(move || {
+        let _ = input.write_all(&stdin);
+    });
+    let stdout = drain(child.stdout.take().expect("stdout is piped"));
+    let stderr = drain(child.stderr.take().expect("stderr is piped"));
+
+    let deadline = Instant::now() + timeout;
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| Error::io(binary, e))? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            timed_out = true;
+            let _ = child.kill();
+            break child.wait().map_err(|e| Error::io(binary, e))?;
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    let _ = writer.join();
+    Ok(RunOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        exit_code: status.code(),
+        timed_out,
+    })
+}
+
+fn drain(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+fn network_namespace_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        cfg!(target_os = "linux")
+            && Command::new("unshare")
+                .args(["--map-root-user", "--net", "true"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success())
+    })
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(expected: &str, stdout: &str) -> Status {
+        compare(expected, stdout, "").status()
+    }
+
+    #[test]
+    fn exact_output_matches() {
+        let c = compare("5! = 120", "5! = 120\n", "");
+        assert_eq!((c.matched, c.total, c.status()), (1, 1, Status::Match));
+        // Whitespace runs do not matter.
+        assert_eq!(status("Visited:  A, B", "Visited: A, B  \n"), Status::Match);
+    }
+
+    #[test]
+    fn lead_ins_and_remarks_are_not_claims() {
+        let expected = "If `data.csv` is as above, it might print:\n\
+                        Processed row: (Alice, 50)\n\
+                        Processed row: (Bob, 75)";
+        let stdout = "Processed row: (Alice, 50)\nProcessed row: (Bob, 75)\n";
+        assert_eq!(compare(expected, stdout, "").total, 2);
+        assert_eq!(status(expected, stdout), Status::Match);
+        let c = compare(
+            "The total sum is: 4950  (if the numbers 1..=100 are used)",
+            "",
+            "",
+        );
+        assert_eq!(c.total, 1);
+    }
+
+    #[test]
+    fn wrong_numbers_are_a_mismatch() {
+        // Snippet 05 sums 1..=100, which is 5050, not the claimed 4950.
+        let expected = "The total sum is: 4950  (if the numbers 1..=100 are used)";
+        assert_eq!(
+            status(expected, "The total sum is: 5050\n"),
+            Status::Mismatch
+        );
+    }
+
+    #[test]
+    fn lines_must_appear_in_order() {
+        let c = compare("one\ntwo\nthree", "one\nthree\ntwo\n", "");
+        assert_eq!((c.matched, c.status()), (2, Status::Partial));
+    }
+
+    #[test]
+    fn typed_input_after_a_prompt_matches_stdin() {
+        let c = compare(
+            "Enter a number: 25\nSquare: 625",
+            "Enter a number:\nSquare: 625\n",
+            "25\n",
+        );
+        assert_eq!(c.status(), Status::Match);
+        let c = compare("Enter a number: 25", "Enter a number:\n", "7\n");
+        assert_eq!(c.status(), Status::Mismatch);
+    }
+
+    #[test]
+    fn prose_descriptions_are_descriptive() {
+        for expected in [
+            "Sıralanmış dizi ekrana yazılır.",
+            "Okunan verinin güncellenmiş hâli `updated_data.json` dosyasına kaydedilir.",
+            "Stack ile push/pop yapılan işlemler ekranda gösterilir.",
+            "vec_of_strings makrosuyla oluşturulan vektördeki elemanlar ekrana yazılır.",
+            "The sorted array is printed to the console.",
+        ] {
+            let c = compare(expected, "[1, 2, 3]\n", "");
+            assert_eq!(
+                (c.total, c.status()),
+                (0, Status::Descriptive),
+                "{}",
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn output_that_looks_like_a_sentence_is_still_a_claim() {
+        assert_eq!(
+            status("Done writing to output.txt", "Done writing to output.txt\n"),
+            Status::Match
+        );
+        assert_eq!(status("Hello, world.", "Hello, world.\n"), Status::Match);
+        // A description next to real output only drops the description.
+        let c = compare("The result is printed.\nResult: 3", "Result: 3\n", "");
+        assert_eq!((c.total, c.status()), (1, Status::Match));
+    }
+
+    #[test]
+    fn no_claim_without_output_lines() {
+        assert_eq!(status("", "anything\n"), Status::NoClaim);
+        assert_eq!(status("It might print:", "anything\n"), Status::NoClaim);
+    }
+}
@@ -0,0 +1,85 @@
+//! The error type shared by every command.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::task::join_ids;
+
+/// Everything that can make a command fail.
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// A line of the todo file, or of an imported file, is malformed.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// No task has this id.
+    NoSuchTask(usize),
+    /// A value given on the command line or in the environment is invalid.
+    InvalidInput(String),
+    /// A git command run by `sync` failed; `message` is what it printed.
+    Git { command: String, message: String },
+    /// A task cannot be marked done while these open tasks, its blockers
+    /// and then its subtasks, are not.
+    Blocked { id: usize, by: Vec<usize> },
+}
+
+impl Error {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// The process exit code reported for the error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Io { .. } => 1,
+            Error::InvalidInput(_) => 2,
+            Error::NoSuchTask(_) => 3,
+            Error::Parse { .. } => 4,
+            Error::Git { .. } => 5,
+            Error::Blocked { .. } => 6,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            }
+            Error::NoSuchTask(id) => write!(f, "No task with id {}", id),
+            Error::InvalidInput(message) => f.write_str(message),
+            Error::Git { command, message } => write!(f, "git {} failed: {}", command, message),
+            Error::Blocked { id, by } => write!(
+                f,
+                "Task {} is blocked by open task(s) {}; use --force to complete it anyway",
+                id,
+                join_ids(by, ", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
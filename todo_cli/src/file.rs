@@ -0,0 +1,664 @@
+//! The todo file: where it is, its format and how it is read and written.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::store::{Interval, TaskList};
+use crate::task::{join_ids, Date, Recurrence, Task, Timestamp};
+
+/// Header written as the first line of every todo file.
+const FORMAT_HEADER: &str = "# todo_cli v";
+/// Version of the format `save_tasks` writes.
+const FORMAT_VERSION: u32 = 8;
+/// Prefix of the line holding the next task id.
+const NEXT_ID_HEADER: &str = "# next_id ";
+/// Name of the list used when no file or list is given.
+const DEFAULT_LIST: &str = "todo";
+
+/// Load tasks from file and parse them into a TaskList. A missing file is
+/// an empty list. Files in older formats are migrated to the current one
+/// first, and what the migration did is returned alongside the list.
+pub(crate) fn load_tasks(file_path: &Path) -> Result<(TaskList, Vec<String>)> {
+    let contents = match fs::read_to_string(file_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::io(file_path, e)),
+    };
+    let (mut list, version) = parse_tasks(file_path, &contents)?;
+    let notices = if version < FORMAT_VERSION {
+        migrate(file_path, &mut list, version)?
+    } else {
+        Vec::new()
+    };
+    Ok((list, notices))
+}
+
+/// Parse the contents of a todo file in any format version, returning the
+/// list and the version it was in. `file_path` names the file in errors.
+pub(crate) fn parse_tasks(file_path: &Path, contents: &str) -> Result<(TaskList, u32)> {
+    if contents.trim().is_empty() {
+        return Ok((TaskList::default(), FORMAT_VERSION));
+    }
+
+    let mut lines = contents.lines().enumerate().peekable();
+    let first = lines.peek().map(|(_, line)| *line).unwrap_or_default();
+    let version = match first.strip_prefix(FORMAT_HEADER) {
+        Some(v) => v.trim().parse::<u32>().map_err(|_| {
+            invalid_data(file_path, 1, &format!("bad version header \"{}\"", first))
+        })?,
+        // No header: the original id|title|done format.
+        None => 1,
+    };
+    if version > FORMAT_VERSION {
+        return Err(invalid_data(
+            file_path,
+            1,
+            &format!(
+                "format version {} is newer than this todo_cli supports",
+                version
+            ),
+        ));
+    }
+    if version > 1 {
+        lines.next();
+    }
+
+    let mut next_id = 1;
+    if version >= 3 {
+        let (n, line) = lines.next().unwrap_or((1, ""));
+        next_id = line
+            .strip_prefix(NEXT_ID_HEADER)
+            .and_then(|id| id.trim().parse().ok())
+            .ok_or_else(|| invalid_data(file_path, n + 1, "expected \"# next_id <n>\""))?;
+    }
+
+    let mut tasks = Vec::new();
+    let mut intervals = Vec::new();
+    for (n, line) in lines {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(fields) = line.strip_prefix("time\t").filter(|_| version >= 8) {
+            let interval =
+                parse_interval(fields).map_err(|msg| invalid_data(file_path, n + 1, &msg))?;
+            intervals.push(interval);
+            continue;
+        }
+        let task = match version {
+            1 => parse_task_v1(line),
+            _ => parse_task(line, version),
+        };
+        // Line numbers are 1-based.
+        tasks.push(task.map_err(|msg| invalid_data(file_path, n + 1, &msg))?);
+    }
+
+    Ok((
+        TaskList {
+            next_id,
+            tasks,
+            intervals,
+        },
+        version,
+    ))
+}
+
+/// Parse the fields of a "time" line: task id, start and end.
+fn parse_interval(fields: &str) -> std::result::Result<Interval, String> {
+    let fields: Vec<&str> = fields.split('\t').collect();
+    let [task, start, end] = fields[..] else {
+        return Err("expected time, task id, start and end".to_string());
+    };
+    Ok(Interval {
+        task: task
+            .parse()
+            .map_err(|_| format!("invalid task id \"{}\"", task))?,
+        start: Timestamp::parse(start)
+            .ok_or_else(|| format!("invalid start time \"{}\"", start))?,
+        end: optional_field(end, "end time", Timestamp::parse)?,
+    })
+}
+
+/// Upgrade a file read in an older format: start the id counter past every
+/// id in use, give fresh ids to tasks that shared one, and rewrite the file
+/// in the current format, keeping a backup of the original next to it.
+/// Returns one line per renumbered task and one for the upgrade.
+fn migrate(file_path: &Path, list: &mut TaskList, from_version: u32) -> Result<Vec<String>> {
+    let past_max = list.tasks.iter().map(|t| t.id + 1).max().unwrap_or(1);
+    list.next_id = list.next_id.max(past_max);
+    let mut notices = Vec::new();
+    let mut seen = Vec::new();
+    for task in list.tasks.iter_mut() {
+        if seen.contains(&task.id) {
+            notices.push(format!(
+                "Task \"{}\" shared id {}; it is now task {}",
+                task.title, task.id, list.next_id
+            ));
+            task.id = list.next_id;
+            list.next_id += 1;
+        }
+        seen.push(task.id);
+    }
+
+    let backup = with_suffix(file_path, &format!(".v{}.bak", from_version));
+    fs::copy(file_path, &backup).map_err(|e| Error::io(&backup, e))?;
+    save_tasks(file_path, list)?;
+    notices.push(format!(
+        "Upgraded {} to format version {} (backup in {})",
+        file_path.display(),
+        FORMAT_VERSION,
+        backup.display()
+    ));
+    Ok(notices)
+}
+
+/// Parse one version 1 line, `id|title|done`.
+fn parse_task_v1(line: &str) -> std::result::Result<Task, String> {
+    // Titles could contain '|', so take the id from the front and the done
+    // flag from the back and keep everything in between.
+    line.split_once('|')
+        .and_then(|(id, rest)| {
+            let (title, done) = rest.rsplit_once('|')?;
+            Some(Task {
+                id: id.parse().ok()?,
+                title: title.to_string(),
+                done: done.parse().ok()?,
+                priority: None,
+                due: None,
+                created: None,
+                completed: None,
+                recurrence: None,
+                modified: None,
+                parent: None,
+                blocked_by: Vec::new(),
+            })
+        })
+        .ok_or_else(|| "expected id|title|done".to_string())
+}
+
+/// Parse one tab-separated task line. Versions 2 and 3 hold id, done and
+/// the escaped title; version 4 adds priority, due, created and completed
+/// before the title, version 5 the recurrence after those, version 6 the
+/// modified time after that and version 7 the parent and blocker ids.
+fn parse_task(line: &str, version: u32) -> std::result::Result<Task, String> {
+    let count = match version {
+        2 | 3 => 3,
+        4 => 7,
+        5 => 8,
+        6 => 9,
+        _ => 11,
+    };
+    let fields: Vec<&str> = line.splitn(count, '\t').collect();
+    if fields.len() != count {
+        return Err(format!("expected {} tab-separated fields", count));
+    }
+    let id = fields[0]
+        .parse()
+        .map_err(|_| format!("invalid task id \"{}\"", fields[0]))?;
+    let done = fields[1]
+        .parse()
+        .map_err(|_| format!("invalid done flag \"{}\"", fields[1]))?;
+    let title = unescape(fields[count - 1])?;
+    let mut task = Task {
+        id,
+        title,
+        done,
+        priority: None,
+        due: None,
+        created: None,
+        completed: None,
+        recurrence: None,
+        modified: None,
+        parent: None,
+        blocked_by: Vec::new(),
+    };
+    if version >= 4 {
+        task.priority = optional_field(fields[2], "priority", |p| {
+            let mut chars = p.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_uppercase() => Some(c),
+                _ => None,
+            }
+        })?;
+        task.due = optional_field(fields[3], "due date", Date::parse)?;
+        task.created = optional_field(fields[4], "created time", Timestamp::parse)?;
+        task.completed = optional_field(fields[5], "completed time", Timestamp::parse)?;
+    }
+    if version >= 5 {
+        task.recurrence = optional_field(fields[6], "recurrence", |rule| {
+            Recurrence::from_rrule(rule).ok()
+        })?;
+    }
+    if version >= 6 {
+        task.modified = optional_field(fields[7], "modified time", Timestamp::parse)?;
+    }
+    if version >= 7 {
+        task.parent = optional_field(fields[8], "parent id", |id| id.parse().ok())?;
+        task.blocked_by = optional_field(fields[9], "blocker ids", |ids| {
+            ids.split(',').map(|id| id.parse().ok()).collect()
+        })?
+        .unwrap_or_default();
+    }
+    Ok(task)
+}
+
+/// Parse a field that is "-" when unset.
+fn optional_field<T>(
+    field: &str,
+    what: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> std::result::Result<Option<T>, String> {
+    if field == "-" {
+        return Ok(None);
+    }
+    parse(field)
+        .map(Some)
+        .ok_or_else(|| format!("invalid {} \"{}\"", what, field))
+}
+
+/// Format a field that may be unset as "-".
+pub fn field<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// Work out which todo file to use: `--file`, then `--list`, then the
+/// `TODO_FILE` environment variable, then the default list.
+pub fn todo_file_path(file: Option<PathBuf>, list: Option<&str>) -> Result<PathBuf> {
+    if let Some(file) = file {
+        return Ok(file);
+    }
+    if list.is_none() {
+        if let Some(file) = env::var_os("TODO_FILE").filter(|f| !f.is_empty()) {
+            return Ok(PathBuf::from(file));
+        }
+    }
+    let name = list.unwrap_or(DEFAULT_LIST);
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(Error::InvalidInput(format!(
+            "Invalid list name \"{}\"; use letters, digits, '-' and '_'",
+            name
+        )));
+    }
+    Ok(data_dir()?.join(format!("{}.txt", name)))
+}
+
+/// The directory holding named lists, following the XDG base directory spec.
+fn data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("todo_cli"));
+    }
+    let home = env::var_os("HOME")
+        .filter(|h| !h.is_empty())
+        .ok_or_else(|| Error::InvalidInput("Neither XDG_DATA_HOME nor HOME is set".to_string()))?;
+    Ok(PathBuf::from(home).join(".local/share/todo_cli"))
+}
+
+/// Wait for an exclusive lock on `<file>.lock`. Other todo_cli processes
+/// block here until the returned handle is dropped.
+pub(crate) fn lock_todo_file(file_path: &Path) -> Result<fs::File> {
+    let lock_path = with_suffix(file_path, ".lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| Error::io(&lock_path, e))?;
+    lock.lock().map_err(|e| Error::io(&lock_path, e))?;
+    Ok(lock)
+}
+
+/// `path` with `suffix` appended to its file name, e.g. todo.txt.lock.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Save tasks back to the file in the current versioned format. The tasks
+/// go to a temporary file first, which then replaces the todo file in one
+/// rename. Callers must hold the lock from `lock_todo_file`.
+pub(crate) fn save_tasks(file_path: &Path, list: &TaskList) -> Result<()> {
+    let mut contents = format!(
+        "{}{}\n{}{}\n",
+        FORMAT_HEADER, FORMAT_VERSION, NEXT_ID_HEADER, list.next_id
+    );
+    for task in &list.tasks {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            task.id,
+            task.done,
+            field(task.priority),
+            field(task.due),
+            field(task.created),
+            field(task.completed),
+            field(task.recurrence.as_ref().map(Recurrence::to_rrule)),
+            field(task.modified),
+            field(task.parent),
+            field((!task.blocked_by.is_empty()).then(|| join_ids(&task.blocked_by, ","))),
+            escape(&task.title)
+        ));
+    }
+    for interval in &list.intervals {
+        contents.push_str(&format!(
+            "time\t{}\t{}\t{}\n",
+            interval.task,
+            interval.start,
+            field(interval.end)
+        ));
+    }
+
+    let temp_path = with_suffix(file_path, ".tmp");
+    let write = || -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        // Make sure the data is on disk before the rename makes it visible.
+        file.sync_all()
+    };
+    write().map_err(|e| Error::io(&temp_path, e))?;
+    fs::rename(&temp_path, file_path).map_err(|e| Error::io(file_path, e))
+}
+
+/// Escape the characters that would break the line and field structure.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reverse `escape`.
+fn unescape(text: &str) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => return Err(format!("invalid escape \"\\{}\"", other)),
+            None => return Err("title ends with a lone backslash".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// Build the error reported for a malformed line of the todo file.
+pub(crate) fn invalid_data(file_path: &Path, line: usize, message: &str) -> Error {
+    Error::Parse {
+        path: file_path.to_path_buf(),
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    /// A todo file in a fresh directory of its own.
+    fn todo_file(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("todo_cli-file-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("todo.txt")
+    }
+
+    fn parse(contents: &str) -> Result<(TaskList, u32)> {
+        parse_tasks(Path::new("todo.txt"), contents)
+    }
+
+    /// The line and message of a parse error.
+    fn parse_error(contents: &str) -> (usize, String) {
+        match parse(contents) {
+            Err(Error::Parse { line, message, .. }) => (line, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    fn titles(list: &TaskList) -> Vec<(usize, &str)> {
+        list.tasks
+            .iter()
+            .map(|task| (task.id, task.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn escape_round_trips_every_special_character() {
+        let title = "a|b\tc\nd\re\\f \\t";
+        assert_eq!(escape(title), "a|b\\tc\\nd\\re\\\\f \\\\t");
+        assert_eq!(unescape(&escape(title)).unwrap(), title);
+        assert_eq!(
+            unescape("bad\\x"),
+            Err("invalid escape \"\\x\"".to_string())
+        );
+        assert_eq!(
+            unescape("trailing\\"),
+            Err("title ends with a lone backslash".to_string())
+        );
+    }
+
+    #[test]
+    fn saved_tasks_read_back_unchanged() {
+        let path = todo_file("round-trip");
+        let mut full = Task::from_todo_txt("(B) Pay | rent +home due:2026-11-01").unwrap();
+        full.id = 3;
+        full.title = "Pay | rent\twith \\ and\nnewline +home".to_string();
+        full.created = Timestamp::parse("2026-10-01T08:00:00Z");
+        full.modified = Timestamp::parse("2026-10-02T09:30:00Z");
+        full.recurrence = Recurrence::from_rrule("FREQ=WEEKLY;BYDAY=MO").ok();
+        full.parent = Some(1);
+        full.blocked_by = vec![1, 2];
+        let mut done = Task::from_todo_txt("Done already").unwrap();
+        done.id = 1;
+        done.done = true;
+        done.completed = Timestamp::parse("2026-10-03T10:00:00Z");
+        let list = TaskList {
+            next_id: 7,
+            tasks: vec![full, done],
+            intervals: vec![
+                Interval {
+                    task: 3,
+                    start: Timestamp(100),
+                    end: Some(Timestamp(200)),
+                },
+                Interval {
+                    task: 1,
+                    start: Timestamp(300),
+                    end: None,
+                },
+            ],
+        };
+
+        save_tasks(&path, &list).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# todo_cli v8\n# next_id 7\n"));
+        assert_eq!(contents.lines().count(), 6);
+        assert!(!with_suffix(&path, ".tmp").exists());
+
+        let (loaded, version) = parse(&contents).unwrap();
+        assert_eq!(version, FORMAT_VERSION);
+        assert_eq!(loaded.next_id, 7);
+        assert_eq!(loaded.tasks, list.tasks);
+        assert_eq!(loaded.intervals, list.intervals);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn reads_the_original_pipe_format() {
+        let (list, version) = parse("1|Buy | milk|false\n2|Call mum|true\n").unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(titles(&list), [(1, "Buy | milk"), (2, "Call mum")]);
+        assert!(list.tasks[1].done);
+        assert_eq!(
+            parse_error("1|Fine|false\n2|no done flag\n"),
+            (2, "expected id|title|done".to_string())
+        );
+    }
+
+    #[test]
+    fn reads_older_tab_separated_versions() {
+        let (list, version) = parse("# todo_cli v2\n4\tfalse\tTab\\there\n").unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(list.next_id, 1);
+        assert_eq!(titles(&list), [(4, "Tab\there")]);
+
+        let (list, version) =
+            parse("# todo_cli v4\n# next_id 9\n2\ttrue\tA\t2026-11-01\t-\t-\tOld\n").unwrap();
+        assert_eq!(version, 4);
+        assert_eq!(list.next_id, 9);
+        assert_eq!(list.tasks[0].priority, Some('A'));
+        assert_eq!(list.tasks[0].due, Date::parse("2026-11-01"));
+    }
+
+    #[test]
+    fn rejects_unknown_and_newer_headers() {
+        assert_eq!(
+            parse_error("# todo_cli v9\n# next_id 1\n"),
+            (
+                1,
+                "format version 9 is newer than this todo_cli supports".to_string()
+            )
+        );
+        assert_eq!(
+            parse_error("# todo_cli vX\n"),
+            (1, "bad version header \"# todo_cli vX\"".to_string())
+        );
+        assert_eq!(
+            parse_error("# todo_cli v8\n1\tfalse\n"),
+            (2, "expected \"# next_id <n>\"".to_string())
+        );
+        assert_eq!(
+            parse_error("# todo_cli v8\n# next_id 2\n1\tmaybe\t-\t-\t-\t-\t-\t-\t-\t-\tX\n"),
+            (3, "invalid done flag \"maybe\"".to_string())
+        );
+    }
+
+    #[test]
+    fn migration_renumbers_shared_ids_and_keeps_a_backup() {
+        let path = todo_file("migrate");
+        let original = "1|Buy | milk|false\n1|Call mum|true\n3|Water plants|false\n";
+        fs::write(&path, original).unwrap();
+
+        let (list, notices) = load_tasks(&path).unwrap();
+        assert_eq!(
+            titles(&list),
+            [(1, "Buy | milk"), (4, "Call mum"), (3, "Water plants")]
+        );
+        assert_eq!(list.next_id, 5);
+        let backup = with_suffix(&path, ".v1.bak");
+        assert_eq!(
+            notices,
+            [
+                "Task \"Call mum\" shared id 1; it is now task 4".to_string(),
+                format!(
+                    "Upgraded {} to format version 8 (backup in {})",
+                    path.display(),
+                    backup.display()
+                )
+            ]
+        );
+        assert_eq!(fs::read_to_string(&backup).unwrap(), original);
+
+        // The file is now in the current format and loads without notices.
+        let (again, notices) = load_tasks(&path).unwrap();
+        assert!(notices.is_empty());
+        assert_eq!(again.tasks, list.tasks);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("# todo_cli v8\n# next_id 5\n"));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn a_missing_file_is_an_empty_list() {
+        let path = todo_file("missing");
+        let (list, notices) = load_tasks(&path).unwrap();
+        assert!(list.tasks.is_empty() && notices.is_empty());
+        assert_eq!(list.next_id, 1);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    // The environment is shared by every test in the process, so all the
+    // cases that change it run in this one test.
+    #[test]
+    fn todo_file_path_precedence() {
+        let saved: Vec<_> = ["TODO_FILE", "XDG_DATA_HOME", "HOME"]
+            .iter()
+            .map(|&var| (var, env::var_os(var)))
+            .collect();
+        let path = |file: Option<&str>, list| todo_file_path(file.map(PathBuf::from), list);
+
+        env::set_var("TODO_FILE", "/env/todo.txt");
+        env::set_var("XDG_DATA_HOME", "/xdg");
+        env::set_var("HOME", "/home/me");
+        assert_eq!(
+            path(Some("a.txt"), Some("work")).unwrap(),
+            Path::new("a.txt")
+        );
+        assert_eq!(
+            path(None, Some("work")).unwrap(),
+            Path::new("/xdg/todo_cli/work.txt")
+        );
+        assert_eq!(path(None, None).unwrap(), Path::new("/env/todo.txt"));
+
+        env::set_var("TODO_FILE", "");
+        assert_eq!(
+            path(None, None).unwrap(),
+            Path::new("/xdg/todo_cli/todo.txt")
+        );
+        env::remove_var("TODO_FILE");
+        assert_eq!(
+            path(None, None).unwrap(),
+            Path::new("/xdg/todo_cli/todo.txt")
+        );
+
+        env::set_var("XDG_DATA_HOME", "");
+        assert_eq!(
+            path(None, Some("work")).unwrap(),
+            Path::new("/home/me/.local/share/todo_cli/work.txt")
+        );
+        env::remove_var("HOME");
+        assert!(matches!(path(None, None), Err(Error::InvalidInput(_))));
+        assert_eq!(path(Some("a.txt"), None).unwrap(), Path::new("a.txt"));
+
+        env::set_var("HOME", "/home/me");
+        for name in ["", "../work", "my list", "work.txt", "työ"] {
+            let err = path(None, Some(name)).unwrap_err();
+            assert!(err.to_string().starts_with("Invalid list name"), "{}", err);
+        }
+        assert!(path(None, Some("home-2_b")).is_ok());
+
+        for (var, value) in saved {
+            match value {
+                Some(value) => env::set_var(var, value),
+                None => env::remove_var(var),
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! A todo list kept in a plain text file.
+//!
+//! [`TaskStore`] does the work behind every `todo_cli` command and returns
+//! tasks instead of printing them; the binary only parses arguments and
+//! prints results. The store saves each change through a [`Storage`]
+//! backend: [`FileStorage`] for locked todo files, or [`MemoryStorage`] to
+//! use it without a filesystem. The commands and the file format are
+//! described in the crate's README.
+
+pub mod error;
+pub mod file;
+pub mod store;
+pub mod sync;
+pub mod task;
+
+pub use error::{Error, Result};
+pub use store::{
+    Completion, FileStorage, Interval, ListOptions, MemoryStorage, Report, SortKey, Storage,
+    TaskList, TaskStore,
+};
+pub use sync::{merge_lists, sync};
+pub use task::{Date, Frequency, Recurrence, Task, Timestamp};
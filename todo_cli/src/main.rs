@@ -0,0 +1,628 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use todo_cli::file::{escape, field, todo_file_path};
+use todo_cli::task::{join_ids, parse_date};
+use todo_cli::{
+    sync, Date, Error, FileStorage, Interval, ListOptions, Report, Result, Storage, Task,
+    TaskStore, Timestamp,
+};
+
+mod tui;
+
+/// A command-line todo list.
+#[derive(Debug, Parser)]
+#[command(name = "todo_cli", version)]
+struct Cli {
+    /// Use this todo file instead of TODO_FILE or the default list.
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "list")]
+    file: Option<PathBuf>,
+    /// Use the named list <data dir>/<NAME>.txt.
+    #[arg(long, global = true, value_name = "NAME")]
+    list: Option<String>,
+    /// How to print tasks.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: Format,
+    /// Print tasks as tab-separated lines in a layout that never changes.
+    #[arg(long, global = true, conflicts_with = "format")]
+    porcelain: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// How commands print tasks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    #[default]
+    Human,
+    Json,
+    Csv,
+    Table,
+    /// Chosen with `--porcelain` rather than `--format`.
+    #[value(skip)]
+    Porcelain,
+}
+
+/// How `link` relates its two tasks.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Relation {
+    Blocks,
+    BlockedBy,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Add a task. "(A)", "due:YYYY-MM-DD", +project and @context are parsed.
+    Add {
+        /// Add it as a subtask of this task.
+        #[arg(long, value_name = "ID")]
+        parent: Option<usize>,
+        #[arg(required = true, allow_hyphen_values = true)]
+        text: Vec<String>,
+    },
+    /// List tasks.
+    List(ListOptions),
+    /// Mark a task as done. Exits with 6 when open blockers or subtasks
+    /// keep it from being done.
+    Done {
+        id: usize,
+        /// Complete it even though it has open blockers or subtasks.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Mark a task as not done.
+    Undone { id: usize },
+    /// Replace a task's text: its title, priority, due date and recurrence.
+    Edit {
+        id: usize,
+        #[arg(required = true, allow_hyphen_values = true)]
+        text: Vec<String>,
+    },
+    /// Remove a task.
+    Rm { id: usize },
+    /// Move a task to a position in the list; 1 is the top.
+    Mv { id: usize, position: usize },
+    /// Make a task a subtask of another, or a top-level task without PARENT.
+    Parent { id: usize, parent: Option<usize> },
+    /// Record that a task blocks, or is blocked by, another.
+    Link {
+        id: usize,
+        #[arg(value_enum)]
+        relation: Relation,
+        other: usize,
+    },
+    /// Remove the blocking link between two tasks.
+    Unlink { id: usize, other: usize },
+    /// Remove every done task.
+    Clear {
+        /// Required, so a bare "clear" cannot empty the list by accident.
+        #[arg(long, required = true)]
+        done: bool,
+    },
+    /// Add the tasks of a todo.txt-format file.
+    Import {
+        #[arg(value_name = "FILE")]
+        source: PathBuf,
+    },
+    /// Write all tasks in todo.txt format, to stdout by default.
+    Export {
+        #[arg(value_name = "FILE")]
+        target: Option<PathBuf>,
+    },
+    /// Print a shell completion script.
+    Completions { shell: Shell },
+    /// List overdue open tasks and those due soon.
+    Due {
+        /// How many days ahead count as upcoming.
+        #[arg(long, value_name = "DAYS", default_value_t = 7)]
+        within: u32,
+    },
+    /// Commit the todo file, merge the upstream branch and push.
+    Sync,
+    /// Start tracking time on a task, stopping any other.
+    Start { id: usize },
+    /// Stop tracking time.
+    Stop,
+    /// Show tracked time per task and per tag.
+    Report {
+        /// Only count time from midnight UTC of this date (YYYY-MM-DD).
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        since: Option<Date>,
+    },
+    /// Open the full-screen terminal interface.
+    Tui {
+        /// Run without a terminal: feed these keys, then print the screen.
+        #[arg(long, value_name = "KEYS")]
+        keys: Option<String>,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("todo_cli: {}", err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+/// Run the parsed command against the chosen todo file.
+fn run(cli: Cli) -> Result<()> {
+    if let Command::Completions { shell } = cli.command {
+        // Generate into a buffer: clap_complete panics on write errors.
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut Cli::command(), "todo_cli", &mut script);
+        return io::stdout()
+            .write_all(&script)
+            .map_err(|e| Error::io("<stdout>", e));
+    }
+
+    let format = if cli.porcelain {
+        Format::Porcelain
+    } else {
+        cli.format
+    };
+    let todo_file = todo_file_path(cli.file, cli.list.as_deref())?;
+    if let Command::Tui { keys } = cli.command {
+        return tui::tui(todo_file, keys.as_deref());
+    }
+    if let Command::Sync = cli.command {
+        for line in sync(&todo_file)? {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    let mut store = TaskStore::open(FileStorage::open(todo_file)?)?;
+    for notice in store.notices() {
+        eprintln!("todo_cli: {}", notice);
+    }
+
+    match cli.command {
+        Command::Add { parent, text } => {
+            let text = text.join(" ");
+            let task = match parent {
+                Some(parent) => store.add_subtask(parent, &text)?,
+                None => store.add(&text)?,
+            };
+            print_changed(
+                format,
+                &format!("Added task {}: \"{}\"", task.id, task.title),
+                task,
+            );
+        }
+        Command::List(options) if options.tree => print_tree(format, &store, &store.tree(&options)),
+        Command::List(options) => print_tasks(format, &store.list(&options)),
+        Command::Done { id, force } => {
+            let completion = store.set_done(id, true, force)?;
+            let task = completion.task;
+            let mut message = format!("Completed task {}: \"{}\"", task.id, task.title);
+            if let Some(next) = completion.next {
+                let due = next.due.map(|d| d.to_string()).unwrap_or_default();
+                message.push_str(&format!(
+                    "\nNext occurrence is task {}, due {}",
+                    next.id, due
+                ));
+            }
+            for parent in completion.parents {
+                message.push_str(&format!(
+                    "\nCompleted parent task {}: \"{}\"",
+                    parent.id, parent.title
+                ));
+            }
+            print_changed(format, &message, task);
+        }
+        Command::Undone { id } => {
+            let completion = store.set_done(id, false, false)?;
+            let task = completion.task;
+            let mut message = format!("Reopened task {}: \"{}\"", task.id, task.title);
+            for parent in completion.parents {
+                message.push_str(&format!(
+                    "\nReopened parent task {}: \"{}\"",
+                    parent.id, parent.title
+                ));
+            }
+            print_changed(format, &message, task);
+        }
+        Command::Edit { id, text } => {
+            let task = store.edit(id, &text.join(" "))?;
+            print_changed(
+                format,
+                &format!("Edited task {}: \"{}\"", task.id, task.title),
+                task,
+            );
+        }
+        Command::Rm { id } => {
+            let task = store.remove(id)?;
+            print_changed(
+                format,
+                &format!("Removed task {}: \"{}\"", task.id, task.title),
+                &task,
+            );
+        }
+        Command::Mv { id, position } => {
+            let (position, task) = store.move_to(id, position)?;
+            let message = format!(
+                "Moved task {} to position {}: \"{}\"",
+                task.id, position, task.title
+            );
+            print_changed(format, &message, task);
+        }
+        Command::Parent { id, parent } => {
+            let task = store.set_parent(id, parent)?;
+            let message = match task.parent {
+                Some(parent) => {
+                    format!(
+                        "Task {} is now a subtask of task {}: \"{}\"",
+                        task.id, parent, task.title
+                    )
+                }
+                None => format!(
+                    "Task {} is now a top-level task: \"{}\"",
+                    task.id, task.title
+                ),
+            };
+            print_changed(format, &message, task);
+        }
+        Command::Link {
+            id,
+            relation,
+            other,
+        } => {
+            let (blocked, blocker) = match relation {
+                Relation::Blocks => (other, id),
+                Relation::BlockedBy => (id, other),
+            };
+            let task = store.link(blocked, blocker)?;
+            let message = format!(
+                "Task {} is blocked by task {}: \"{}\"",
+                task.id, blocker, task.title
+            );
+            print_changed(format, &message, task);
+        }
+        Command::Unlink { id, other } => {
+            store.unlink(id, other)?;
+            println!("Tasks {} and {} are no longer linked", id, other);
+        }
+        Command::Clear { .. } => {
+            let removed = store.clear_done()?;
+            println!("Removed {} done task(s)", removed.len());
+        }
+        Command::Due { within } => {
+            let (overdue, upcoming) = store.due(Date::today(), within as i64);
+            if format == Format::Human {
+                println!("Overdue:");
+                print_tasks(format, &overdue);
+                println!("Due within {} days:", within);
+                print_tasks(format, &upcoming);
+            } else {
+                print_tasks(format, &[overdue, upcoming].concat());
+            }
+        }
+        Command::Import { source } => {
+            let text = fs::read_to_string(&source).map_err(|e| Error::io(&source, e))?;
+            let count = store.import_todo_txt(&source, &text)?;
+            println!("Imported {} tasks from {}", count, source.display());
+        }
+        Command::Export { target } => {
+            let text = store.to_todo_txt();
+            match target {
+                Some(path) => fs::write(&path, text).map_err(|e| Error::io(&path, e))?,
+                None => io::stdout()
+                    .write_all(text.as_bytes())
+                    .map_err(|e| Error::io("<stdout>", e))?,
+            }
+        }
+        Command::Start { id } => {
+            print_stopped(&store.start(id)?);
+            let task = store.task(id)?;
+            println!("Tracking task {}: \"{}\"", task.id, task.title);
+        }
+        Command::Stop => print_stopped(&store.stop()?),
+        Command::Report { since } => {
+            let report = store.report(since.map(Date::start), Timestamp::now());
+            print_report(format, &store, &report);
+        }
+        Command::Completions { .. } | Command::Tui { .. } | Command::Sync => {
+            unreachable!("handled above")
+        }
+    }
+    Ok(())
+}
+
+/// Print the task a command changed: `message` in the human format, the
+/// task itself in the others.
+fn print_changed(format: Format, message: &str, task: &Task) {
+    match format {
+        Format::Human => println!("{}", message),
+        Format::Json => println!("{}", task_json(task)),
+        _ => print_tasks(format, &[task]),
+    }
+}
+
+/// The CSV header for tasks. Columns are only ever added at the end, so
+/// readers that go by position keep working: the first layout ended at
+/// `completed`, recurring tasks added `recurrence`, and subtasks added
+/// `parent` and `blocked_by`.
+const CSV_COLUMNS: &str =
+    "id,title,done,priority,due,created,completed,recurrence,parent,blocked_by";
+
+/// Print a list of tasks in `format`.
+fn print_tasks(format: Format, tasks: &[&Task]) {
+    match format {
+        Format::Human => {
+            for task in tasks {
+                println!("{}", human_line(task));
+            }
+        }
+        Format::Json => {
+            let objects: Vec<String> = tasks.iter().map(|task| task_json(task)).collect();
+            println!("[{}]", objects.join(","));
+        }
+        Format::Csv => {
+            println!("{}", CSV_COLUMNS);
+            for task in tasks {
+                println!(
+                    "{},{},{},{},{},{},{},{},{},{}",
+                    task.id,
+                    csv_field(&task.title),
+                    task.done,
+                    optional(task.priority),
+                    optional(task.due),
+                    optional(task.created),
+                    optional(task.completed),
+                    csv_field(&optional(task.recurrence.as_ref())),
+                    optional(task.parent),
+                    join_ids(&task.blocked_by, " ")
+                );
+            }
+        }
+        Format::Table => {
+            let header = ["ID", "DONE", "PRI", "DUE", "TITLE"];
+            let rows: Vec<[String; 5]> = tasks
+                .iter()
+                .map(|task| {
+                    [
+                        task.id.to_string(),
+                        if task.done { "x" } else { "" }.to_string(),
+                        optional(task.priority),
+                        optional(task.due),
+                        task.title.replace(['\t', '\r', '\n'], " "),
+                    ]
+                })
+                .collect();
+            let mut widths = header.map(|h| h.len());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            let print_row = |cells: [&str; 5]| {
+                let mut line = String::new();
+                for (cell, width) in cells.iter().zip(widths).take(4) {
+                    line.push_str(&format!("{:<width$}  ", cell, width = width));
+                }
+                line.push_str(cells[4]);
+                println!("{}", line);
+            };
+            print_row(header);
+            for row in &rows {
+                print_row([&row[0], &row[1], &row[2], &row[3], &row[4]]);
+            }
+        }
+        Format::Porcelain => {
+            for task in tasks {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    task.id,
+                    if task.done { "done" } else { "open" },
+                    field(task.priority),
+                    field(task.due),
+                    field(task.created),
+                    field(task.completed),
+                    escape(&task.title)
+                );
+            }
+        }
+    }
+}
+
+/// Print tasks from `TaskStore::tree`: indented with their subtask counts
+/// in the human format, in the same order without indenting in the others.
+fn print_tree<S: Storage>(format: Format, store: &TaskStore<S>, tree: &[(usize, &Task)]) {
+    if format != Format::Human {
+        let tasks: Vec<&Task> = tree.iter().map(|&(_, task)| task).collect();
+        print_tasks(format, &tasks);
+        return;
+    }
+    for &(depth, task) in tree {
+        let subtasks = store.subtasks(task.id);
+        let mut line = format!("{}{}", "  ".repeat(depth), human_line(task));
+        if !subtasks.is_empty() {
+            let done = subtasks.iter().filter(|t| t.done).count();
+            line.push_str(&format!(" [{}/{}]", done, subtasks.len()));
+        }
+        println!("{}", line);
+    }
+}
+
+/// Print a time report: sections per task and per tag in the human and
+/// table formats, rows in the others. See "Time tracking" in the header.
+fn print_report<S: Storage>(format: Format, store: &TaskStore<S>, report: &Report) {
+    let title = |id: usize| {
+        store
+            .tasks()
+            .iter()
+            .find(|task| task.id == id)
+            .map_or_else(|| "(removed)".to_string(), |task| task.title.clone())
+    };
+    let hours = |seconds: i64| format!("{:.2}", seconds as f64 / 3600.0);
+    match format {
+        Format::Human | Format::Table => {
+            println!("By task:");
+            for &(id, seconds) in &report.tasks {
+                println!("  {:>8}  {}: {}", duration(seconds), id, title(id));
+            }
+            println!("By tag:");
+            for (tag, seconds) in &report.tags {
+                println!("  {:>8}  {}", duration(*seconds), tag);
+            }
+        }
+        Format::Json => {
+            let tasks: Vec<String> = report
+                .tasks
+                .iter()
+                .map(|&(id, seconds)| {
+                    format!(
+                        "{{\"id\":{},\"title\":{},\"seconds\":{}}}",
+                        id,
+                        json_string(&title(id)),
+                        seconds
+                    )
+                })
+                .collect();
+            let tags: Vec<String> = report
+                .tags
+                .iter()
+                .map(|(tag, seconds)| {
+                    format!("{{\"tag\":{},\"seconds\":{}}}", json_string(tag), seconds)
+                })
+                .collect();
+            println!(
+                "{{\"tasks\":[{}],\"tags\":[{}]}}",
+                tasks.join(","),
+                tags.join(",")
+            );
+        }
+        Format::Csv | Format::Porcelain => {
+            let rows = report
+                .tasks
+                .iter()
+                .map(|&(id, seconds)| ("task", id.to_string(), title(id), seconds))
+                .chain(
+                    report
+                        .tags
+                        .iter()
+                        .map(|(tag, seconds)| ("tag", String::new(), tag.clone(), *seconds)),
+                );
+            if format == Format::Csv {
+                println!("group,id,name,seconds,hours");
+                for (group, id, name, seconds) in rows {
+                    println!(
+                        "{},{},{},{},{}",
+                        group,
+                        id,
+                        csv_field(&name),
+                        seconds,
+                        hours(seconds)
+                    );
+                }
+            } else {
+                for (group, id, name, seconds) in rows {
+                    let id = field(Some(id).filter(|id| !id.is_empty()));
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        group,
+                        id,
+                        escape(&name),
+                        seconds,
+                        hours(seconds)
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Report the intervals `start` or `stop` ended.
+fn print_stopped(stopped: &[Interval]) {
+    for interval in stopped {
+        let seconds = interval.seconds(None, Timestamp::now());
+        println!("Stopped task {} after {}", interval.task, duration(seconds));
+    }
+}
+
+/// A number of seconds as "2h 05m", "12m" or "40s".
+fn duration(seconds: i64) -> String {
+    match (seconds / 3600, seconds % 3600 / 60) {
+        (0, 0) => format!("{}s", seconds),
+        (0, minutes) => format!("{}m", minutes),
+        (hours, minutes) => format!("{}h {:02}m", hours, minutes),
+    }
+}
+
+/// One task as `list` shows it by default: "1: [ ] (A) title due:...",
+/// followed by the ids of its blockers as "blocked-by:3,5".
+fn human_line(task: &Task) -> String {
+    let status = if task.done { "[x]" } else { "[ ]" };
+    let mut line = format!("{}: {} {}", task.id, status, task.text());
+    if !task.blocked_by.is_empty() && !task.done {
+        line.push_str(&format!(" blocked-by:{}", join_ids(&task.blocked_by, ",")));
+    }
+    line
+}
+
+/// One task as a JSON object.
+fn task_json(task: &Task) -> String {
+    let string_or_null = |value: String| {
+        if value.is_empty() {
+            "null".to_string()
+        } else {
+            json_string(&value)
+        }
+    };
+    let tags: Vec<String> = task.tags().map(json_string).collect();
+    format!(
+        "{{\"id\":{},\"title\":{},\"done\":{},\"priority\":{},\"due\":{},\"created\":{},\"completed\":{},\"recurrence\":{},\"parent\":{},\"blocked_by\":[{}],\"tags\":[{}]}}",
+        task.id,
+        json_string(&task.title),
+        task.done,
+        string_or_null(optional(task.priority)),
+        string_or_null(optional(task.due)),
+        string_or_null(optional(task.created)),
+        string_or_null(optional(task.completed)),
+        string_or_null(optional(task.recurrence.as_ref())),
+        task.parent.map_or_else(|| "null".to_string(), |id| id.to_string()),
+        join_ids(&task.blocked_by, ","),
+        tags.join(",")
+    )
+}
+
+/// `text` as a quoted JSON string.
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `text` as a CSV field, quoted when it holds a comma, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// A value that may be unset, as an empty string when it is.
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
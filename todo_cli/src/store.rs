@@ -0,0 +1,1069 @@
+//! The task list and the storage it is saved to.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+
+use crate::error::{Error, Result};
+use crate::file::{invalid_data, load_tasks, lock_todo_file, save_tasks};
+use crate::task::{join_ids, parse_date, Date, Task, Timestamp};
+
+/// Which tasks `list` shows and in what order.
+#[derive(Debug, Default, Args)]
+pub struct ListOptions {
+    /// Only tasks due before this date (YYYY-MM-DD).
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub due_before: Option<Date>,
+    /// Only tasks with this +project or @context tag; repeat to require several.
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    /// Order of the listed tasks; ties keep their list order.
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortKey,
+    /// Show subtasks indented under their parents.
+    #[arg(long)]
+    pub tree: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// The order of the list, as arranged with `mv`.
+    #[default]
+    Position,
+    Id,
+    Priority,
+    Due,
+    Created,
+    Completed,
+}
+
+/// Everything stored in the todo file.
+#[derive(Debug, Clone)]
+pub struct TaskList {
+    /// Id the next added task receives. Only ever grows.
+    pub next_id: usize,
+    pub tasks: Vec<Task>,
+    /// Time tracked on tasks, oldest first.
+    pub intervals: Vec<Interval>,
+}
+
+impl Default for TaskList {
+    fn default() -> Self {
+        TaskList {
+            next_id: 1,
+            tasks: Vec::new(),
+            intervals: Vec::new(),
+        }
+    }
+}
+
+/// A stretch of time spent on a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interval {
+    pub task: usize,
+    pub start: Timestamp,
+    /// `None` while the task is being tracked.
+    pub end: Option<Timestamp>,
+}
+
+impl Interval {
+    /// Seconds of the interval after `since`, counting a running one up to
+    /// `now`.
+    pub fn seconds(&self, since: Option<Timestamp>, now: Timestamp) -> i64 {
+        let start = since.map_or(self.start, |since| self.start.max(since));
+        (self.end.unwrap_or(now).0 - start.0).max(0)
+    }
+}
+
+/// Tracked time added up by `TaskStore::report`, most time first.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Task ids with their seconds.
+    pub tasks: Vec<(usize, i64)>,
+    /// Tags with the seconds of the tasks carrying them.
+    pub tags: Vec<(String, i64)>,
+}
+
+/// A task list and the storage it is saved to. Each method that changes
+/// the list saves it before returning.
+pub struct TaskStore<S: Storage> {
+    storage: S,
+    list: TaskList,
+    notices: Vec<String>,
+}
+
+impl<S: Storage> TaskStore<S> {
+    /// Load the list from `storage`.
+    pub fn open(mut storage: S) -> Result<Self> {
+        let list = storage.load()?;
+        let notices = storage.take_notices();
+        Ok(TaskStore {
+            storage,
+            list,
+            notices,
+        })
+    }
+
+    /// What loading changed in the stored list, such as a format upgrade,
+    /// one line each; for the caller to show.
+    pub fn notices(&self) -> &[String] {
+        &self.notices
+    }
+
+    /// All tasks, in list order.
+    pub fn tasks(&self) -> &[Task] {
+        &self.list.tasks
+    }
+
+    /// The tasks that match `options`, in the order it asks for.
+    pub fn list(&self, options: &ListOptions) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .tasks()
+            .iter()
+            .filter(|task| match (options.due_before, task.due) {
+                (Some(before), Some(due)) => due < before,
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .filter(|task| options.tags.iter().all(|tag| task.tags().any(|t| t == tag)))
+            .collect();
+        // Stable sort: ties keep their list order. Unset values go last.
+        match options.sort {
+            SortKey::Position => {}
+            SortKey::Id => tasks.sort_by_key(|task| task.id),
+            SortKey::Priority => tasks.sort_by(|a, b| unset_last(a.priority, b.priority)),
+            SortKey::Due => tasks.sort_by(|a, b| unset_last(a.due, b.due)),
+            SortKey::Created => tasks.sort_by(|a, b| unset_last(a.created, b.created)),
+            SortKey::Completed => tasks.sort_by(|a, b| unset_last(a.completed, b.completed)),
+        }
+        tasks
+    }
+
+    /// Add a task from todo.txt-style text; see `Task::apply_text`.
+    pub fn add(&mut self, text: &str) -> Result<&Task> {
+        self.insert(text, None)
+    }
+
+    /// Add a task as a subtask of `parent`, reopening the parent (and its
+    /// ancestors) if it was done.
+    pub fn add_subtask(&mut self, parent: usize, text: &str) -> Result<&Task> {
+        self.index_of(parent)?;
+        self.insert(text, Some(parent))
+    }
+
+    fn insert(&mut self, text: &str, parent: Option<usize>) -> Result<&Task> {
+        let mut task = Task {
+            id: self.list.next_id,
+            title: String::new(),
+            done: false,
+            priority: None,
+            due: None,
+            created: Some(Timestamp::now()),
+            completed: None,
+            recurrence: None,
+            modified: None,
+            parent: None,
+            blocked_by: Vec::new(),
+        };
+        task.modified = task.created;
+        task.parent = parent;
+        task.apply_text(text)?;
+        if task.due.is_none() {
+            task.due = task.recurrence.as_ref().map(|r| r.first(Date::today()));
+        }
+        self.list.next_id += 1;
+        let now = task.created.unwrap_or_else(Timestamp::now);
+        self.list.tasks.push(task);
+        self.roll_up(parent, now);
+        self.save()?;
+        Ok(&self.list.tasks[self.list.tasks.len() - 1])
+    }
+
+    /// Mark a task as done, or as not done again. A task with open blockers
+    /// or subtasks is only completed with `force`; without it that fails
+    /// with `Error::Blocked`. Completing a recurring
+    /// task adds its next occurrence at the end of the list, and parents
+    /// roll up: see `Completion`.
+    pub fn set_done(&mut self, task_id: usize, done: bool, force: bool) -> Result<Completion<'_>> {
+        let index = self.index_of(task_id)?;
+        if done && !force && !self.list.tasks[index].done {
+            let open = self.open_blockers(&self.list.tasks[index]);
+            if !open.is_empty() {
+                return Err(Error::Blocked {
+                    id: task_id,
+                    by: open,
+                });
+            }
+        }
+        let now = Timestamp::now();
+        let task = &mut self.list.tasks[index];
+        let completing = done && !task.done;
+        // Marking a task as it already is keeps its completion time.
+        if task.done != done {
+            task.done = done;
+            task.completed = done.then_some(now);
+            task.modified = Some(now);
+        }
+        let next = match &task.recurrence {
+            Some(recurrence) if completing => {
+                let today = now.date();
+                // The copy keeps the parent and blockers, so a parent stays
+                // open until its recurring subtasks stop recurring.
+                let mut next = task.clone();
+                next.id = self.list.next_id;
+                next.done = false;
+                next.completed = None;
+                next.created = Some(now);
+                let due = recurrence.next_after(task.due.unwrap_or(today), today);
+                next.due = Some(due);
+                // A due date past what the file can hold ends the series.
+                (due.year <= Date::LAST_YEAR).then_some(next)
+            }
+            _ => None,
+        };
+        let added = next.is_some();
+        if let Some(next) = next {
+            self.list.next_id += 1;
+            self.list.tasks.push(next);
+        }
+        let parents = self.roll_up(self.list.tasks[index].parent, now);
+        self.save()?;
+        Ok(Completion {
+            task: &self.list.tasks[index],
+            next: added.then(|| &self.list.tasks[self.list.tasks.len() - 1]),
+            parents: parents
+                .iter()
+                .filter_map(|&id| self.tasks().iter().find(|t| t.id == id))
+                .collect(),
+        })
+    }
+
+    /// Make a task a subtask of `parent`, or a top-level task with `None`.
+    /// Both the old and the new parent roll up. A parent waits on its
+    /// subtasks, so one the task already waits on is refused.
+    pub fn set_parent(&mut self, task_id: usize, parent: Option<usize>) -> Result<&Task> {
+        let index = self.index_of(task_id)?;
+        if let Some(parent) = parent {
+            self.index_of(parent)?;
+            if let Some(path) = self.wait_path(task_id, parent) {
+                let cycle: Vec<usize> = [parent].into_iter().chain(path).collect();
+                return Err(Error::InvalidInput(format!(
+                    "Task {} cannot be a subtask of task {}: that would be a cycle ({})",
+                    task_id,
+                    parent,
+                    join_ids(&cycle, " -> ")
+                )));
+            }
+        }
+        let now = Timestamp::now();
+        let task = &mut self.list.tasks[index];
+        let old = std::mem::replace(&mut task.parent, parent);
+        task.modified = Some(now);
+        self.roll_up(old, now);
+        self.roll_up(parent, now);
+        self.save()?;
+        Ok(&self.list.tasks[index])
+    }
+
+    /// Record that `task_id` is blocked by `blocker`, refusing links that
+    /// would make a task wait for itself; see `wait_path`.
+    pub fn link(&mut self, task_id: usize, blocker: usize) -> Result<&Task> {
+        let index = self.index_of(task_id)?;
+        self.index_of(blocker)?;
+        if let Some(path) = self.wait_path(blocker, task_id) {
+            let cycle: Vec<usize> = [task_id].into_iter().chain(path).collect();
+            return Err(Error::InvalidInput(format!(
+                "Task {} cannot be blocked by task {}: that would be a cycle ({})",
+                task_id,
+                blocker,
+                join_ids(&cycle, " -> ")
+            )));
+        }
+        let task = &mut self.list.tasks[index];
+        if !task.blocked_by.contains(&blocker) {
+            task.blocked_by.push(blocker);
+            task.modified = Some(Timestamp::now());
+            self.save()?;
+        }
+        Ok(&self.list.tasks[index])
+    }
+
+    /// Remove the blocking link between two tasks, whichever way it goes.
+    pub fn unlink(&mut self, task_id: usize, other: usize) -> Result<()> {
+        let indexes = [self.index_of(task_id)?, self.index_of(other)?];
+        let now = Timestamp::now();
+        let mut found = false;
+        for (index, gone) in indexes.into_iter().zip([other, task_id]) {
+            let task = &mut self.list.tasks[index];
+            if task.blocked_by.contains(&gone) {
+                task.blocked_by.retain(|&id| id != gone);
+                task.modified = Some(now);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(Error::InvalidInput(format!(
+                "Tasks {} and {} are not linked",
+                task_id, other
+            )));
+        }
+        self.save()
+    }
+
+    /// The direct subtasks of a task, in list order.
+    pub fn subtasks(&self, task_id: usize) -> Vec<&Task> {
+        self.tasks()
+            .iter()
+            .filter(|task| task.parent == Some(task_id))
+            .collect()
+    }
+
+    /// The tasks that match `options` with every subtask right after its
+    /// parent, each paired with its depth. Subtasks whose parent is not
+    /// listed are shown at the top level.
+    pub fn tree(&self, options: &ListOptions) -> Vec<(usize, &Task)> {
+        let tasks = self.list(options);
+        let listed =
+            |id: Option<usize>| id.is_some_and(|id| tasks.iter().any(|task| task.id == id));
+        let mut stack: Vec<(usize, &Task)> = tasks
+            .iter()
+            .rev()
+            .filter(|task| !listed(task.parent))
+            .map(|&task| (0, task))
+            .collect();
+        let mut tree: Vec<(usize, &Task)> = Vec::new();
+        while let Some((depth, task)) = stack.pop() {
+            if tree.iter().any(|(_, placed)| placed.id == task.id) {
+                continue;
+            }
+            tree.push((depth, task));
+            let children = tasks
+                .iter()
+                .rev()
+                .filter(|child| child.parent == Some(task.id));
+            stack.extend(children.map(|&child| (depth + 1, child)));
+        }
+        tree
+    }
+
+    /// Replace the title, priority, due date and recurrence of a task with
+    /// those of the new text; ones the text leaves out are cleared.
+    pub fn edit(&mut self, task_id: usize, text: &str) -> Result<&Task> {
+        let index = self.index_of(task_id)?;
+        let task = &mut self.list.tasks[index];
+        task.apply_text(text)?;
+        task.modified = Some(Timestamp::now());
+        self.save()?;
+        Ok(&self.list.tasks[index])
+    }
+
+    /// Remove a task. Its id is not handed out again, its subtasks move up
+    /// to its parent and links to it are dropped.
+    pub fn remove(&mut self, task_id: usize) -> Result<Task> {
+        let index = self.index_of(task_id)?;
+        let task = self.list.tasks.remove(index);
+        self.detach(std::slice::from_ref(&task));
+        self.roll_up(task.parent, Timestamp::now());
+        self.save()?;
+        Ok(task)
+    }
+
+    /// Move a task to a 1-based position in the list. Positions past the
+    /// end move it to the bottom; the position it ends up at is returned.
+    pub fn move_to(&mut self, task_id: usize, position: usize) -> Result<(usize, &Task)> {
+        if position == 0 {
+            return Err(Error::InvalidInput("Positions start at 1".to_string()));
+        }
+        let index = self.index_of(task_id)?;
+        let task = self.list.tasks.remove(index);
+        let position = position.min(self.list.tasks.len() + 1);
+        self.list.tasks.insert(position - 1, task);
+        self.save()?;
+        Ok((position, &self.list.tasks[position - 1]))
+    }
+
+    /// Remove every task that is done, returning them.
+    pub fn clear_done(&mut self) -> Result<Vec<Task>> {
+        let (done, open): (Vec<Task>, Vec<Task>) =
+            self.list.tasks.drain(..).partition(|task| task.done);
+        self.list.tasks = open;
+        self.detach(&done);
+        self.save()?;
+        Ok(done)
+    }
+
+    /// Open tasks due before `today`, and those due from `today` up to
+    /// `within` days later, each sorted by due date.
+    pub fn due(&self, today: Date, within: i64) -> (Vec<&Task>, Vec<&Task>) {
+        let mut dated: Vec<(&Task, Date)> = self
+            .tasks()
+            .iter()
+            .filter(|task| !task.done)
+            .filter_map(|task| task.due.map(|due| (task, due)))
+            .collect();
+        dated.sort_by_key(|&(_, due)| due);
+        let last = today.add_days(within);
+        let overdue = dated
+            .iter()
+            .filter(|&&(_, due)| due < today)
+            .map(|&(task, _)| task)
+            .collect();
+        let upcoming = dated
+            .iter()
+            .filter(|&&(_, due)| due >= today && due <= last)
+            .map(|&(task, _)| task)
+            .collect();
+        (overdue, upcoming)
+    }
+
+    /// Append the tasks of todo.txt-format `text`, giving them new ids, and
+    /// return how many there were. `source` names the text in errors.
+    pub fn import_todo_txt(&mut self, source: &Path, text: &str) -> Result<usize> {
+        let mut tasks = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            tasks.push(Task::from_todo_txt(line).map_err(|msg| invalid_data(source, n + 1, &msg))?);
+        }
+        // Nothing is added unless every line parsed.
+        let count = tasks.len();
+        let now = Timestamp::now();
+        for mut task in tasks {
+            task.id = self.list.next_id;
+            task.modified = Some(now);
+            self.list.next_id += 1;
+            self.list.tasks.push(task);
+        }
+        self.save()?;
+        Ok(count)
+    }
+
+    /// Every task in todo.txt format, one line each.
+    pub fn to_todo_txt(&self) -> String {
+        self.tasks()
+            .iter()
+            .map(|task| task.to_todo_txt() + "\n")
+            .collect()
+    }
+
+    /// Start tracking time on a task, stopping and returning the intervals
+    /// that were running.
+    pub fn start(&mut self, task_id: usize) -> Result<Vec<Interval>> {
+        self.index_of(task_id)?;
+        if self.tracking().any(|interval| interval.task == task_id) {
+            return Err(Error::InvalidInput(format!(
+                "Task {} is already being tracked",
+                task_id
+            )));
+        }
+        let now = Timestamp::now();
+        let stopped = self.end_running(now);
+        self.list.intervals.push(Interval {
+            task: task_id,
+            start: now,
+            end: None,
+        });
+        self.save()?;
+        Ok(stopped)
+    }
+
+    /// Stop tracking time, returning the intervals that were running.
+    pub fn stop(&mut self) -> Result<Vec<Interval>> {
+        let stopped = self.end_running(Timestamp::now());
+        if stopped.is_empty() {
+            return Err(Error::InvalidInput("No task is being tracked".to_string()));
+        }
+        self.save()?;
+        Ok(stopped)
+    }
+
+    /// The intervals still running. There is at most one, unless `sync`
+    /// brought in another from a different machine.
+    pub fn tracking(&self) -> impl Iterator<Item = &Interval> {
+        self.list
+            .intervals
+            .iter()
+            .filter(|interval| interval.end.is_none())
+    }
+
+    /// Add up the time tracked after `since`, per task and per tag.
+    pub fn report(&self, since: Option<Timestamp>, now: Timestamp) -> Report {
+        let mut report = Report::default();
+        for interval in &self.list.intervals {
+            let seconds = interval.seconds(since, now);
+            if seconds == 0 {
+                continue;
+            }
+            match report.tasks.iter_mut().find(|(id, _)| *id == interval.task) {
+                Some((_, total)) => *total += seconds,
+                None => report.tasks.push((interval.task, seconds)),
+            }
+        }
+        for &(id, seconds) in &report.tasks {
+            let Some(task) = self.tasks().iter().find(|task| task.id == id) else {
+                continue;
+            };
+            for tag in task.tags() {
+                match report.tags.iter_mut().find(|(t, _)| t == tag) {
+                    Some((_, total)) => *total += seconds,
+                    None => report.tags.push((tag.to_string(), seconds)),
+                }
+            }
+        }
+        report.tasks.sort_by_key(|&(id, seconds)| (-seconds, id));
+        report
+            .tags
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report
+    }
+
+    fn end_running(&mut self, now: Timestamp) -> Vec<Interval> {
+        let mut stopped = Vec::new();
+        for interval in self
+            .list
+            .intervals
+            .iter_mut()
+            .filter(|interval| interval.end.is_none())
+        {
+            interval.end = Some(now);
+            stopped.push(interval.clone());
+        }
+        stopped
+    }
+
+    /// Ids of the open tasks keeping a task from being done: its blockers,
+    /// then its subtasks.
+    fn open_blockers(&self, task: &Task) -> Vec<usize> {
+        let open = |id: usize| {
+            self.tasks()
+                .iter()
+                .any(|other| other.id == id && !other.done)
+        };
+        let blockers = task.blocked_by.iter().copied().filter(|&id| open(id));
+        let subtasks = self
+            .subtasks(task.id)
+            .into_iter()
+            .filter(|t| !t.done)
+            .map(|t| t.id);
+        blockers.chain(subtasks).collect()
+    }
+
+    /// The chain of tasks leading from `from` to `to`, both included, if
+    /// `from` waits on `to` directly or through other tasks. A task waits
+    /// on its blockers and on its subtasks.
+    fn wait_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut stack = vec![vec![from]];
+        let mut seen = Vec::new();
+        while let Some(path) = stack.pop() {
+            let last = path[path.len() - 1];
+            if last == to {
+                return Some(path);
+            }
+            if seen.contains(&last) {
+                continue;
+            }
+            seen.push(last);
+            let task = self.tasks().iter().find(|task| task.id == last);
+            let blockers = task.map_or(&[][..], |task| &task.blocked_by).iter();
+            let subtasks = self.subtasks(last).into_iter().map(|task| &task.id);
+            for &next in blockers.chain(subtasks) {
+                stack.push(path.iter().copied().chain([next]).collect());
+            }
+        }
+        None
+    }
+
+    /// Bring `parent` and its ancestors in line with their subtasks: done
+    /// once every subtask is, unless the task has open blockers, and open
+    /// again while a subtask is not. Returns the ids of the tasks that
+    /// changed.
+    fn roll_up(&mut self, parent: Option<usize>, now: Timestamp) -> Vec<usize> {
+        let mut changed = Vec::new();
+        let mut next = parent;
+        while let Some(id) = next {
+            let subtasks = self.subtasks(id);
+            if subtasks.is_empty() {
+                break;
+            }
+            let done = subtasks.iter().all(|task| task.done);
+            let Some(index) = self.tasks().iter().position(|task| task.id == id) else {
+                break;
+            };
+            // Blockers hold a parent open as they would a task marked done
+            // by hand; its subtasks are all done here.
+            if done && !self.open_blockers(&self.list.tasks[index]).is_empty() {
+                break;
+            }
+            let task = &mut self.list.tasks[index];
+            // Stop at the first task that stays as it is, or at a cycle in
+            // a hand-edited file.
+            if task.done == done || changed.contains(&id) {
+                break;
+            }
+            task.done = done;
+            task.completed = if done { Some(now) } else { None };
+            task.modified = Some(now);
+            changed.push(id);
+            next = task.parent;
+        }
+        changed
+    }
+
+    /// Drop every reference to the `removed` tasks: their subtasks move up
+    /// to the nearest ancestor that is still there, and links to them go.
+    fn detach(&mut self, removed: &[Task]) {
+        let gone = |id: usize| removed.iter().find(|task| task.id == id);
+        for task in self.list.tasks.iter_mut() {
+            // Bounded, in case removed tasks are each other's parents.
+            for _ in 0..removed.len() {
+                match task.parent.and_then(gone) {
+                    Some(parent) => task.parent = parent.parent,
+                    None => break,
+                }
+            }
+            task.blocked_by.retain(|&id| gone(id).is_none());
+        }
+    }
+
+    /// The task with the id.
+    pub fn task(&self, task_id: usize) -> Result<&Task> {
+        Ok(&self.tasks()[self.index_of(task_id)?])
+    }
+
+    /// Position of a task in the list, failing when no task has the id.
+    fn index_of(&self, task_id: usize) -> Result<usize> {
+        self.tasks()
+            .iter()
+            .position(|task| task.id == task_id)
+            .ok_or(Error::NoSuchTask(task_id))
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.storage.save(&self.list)
+    }
+}
+
+/// What `TaskStore::set_done` changed.
+pub struct Completion<'a> {
+    /// The task that was marked done or not done.
+    pub task: &'a Task,
+    /// The next occurrence added for a completed recurring task.
+    pub next: Option<&'a Task>,
+    /// Ancestors completed or reopened along with the task, nearest first.
+    pub parents: Vec<&'a Task>,
+}
+
+/// Where a task list is kept between runs.
+pub trait Storage {
+    /// Read the list. Storage that was never saved to holds an empty list.
+    fn load(&mut self) -> Result<TaskList>;
+    /// Replace the stored list.
+    fn save(&mut self, list: &TaskList) -> Result<()>;
+    /// What the last `load` changed in the stored list, one line each.
+    fn take_notices(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// A todo file on disk, locked for as long as the value lives.
+pub struct FileStorage {
+    path: PathBuf,
+    /// Released when dropped; see `lock_todo_file`.
+    _lock: fs::File,
+    /// Left by migrating an old file on load.
+    notices: Vec<String>,
+}
+
+impl FileStorage {
+    /// Lock the todo file at `path`, waiting while another process holds
+    /// it. Missing parent directories are created.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        let lock = lock_todo_file(&path)?;
+        Ok(FileStorage {
+            path,
+            _lock: lock,
+            notices: Vec::new(),
+        })
+    }
+}
+
+impl Storage for FileStorage {
+    fn load(&mut self) -> Result<TaskList> {
+        let (list, notices) = load_tasks(&self.path)?;
+        self.notices = notices;
+        Ok(list)
+    }
+
+    fn save(&mut self, list: &TaskList) -> Result<()> {
+        save_tasks(&self.path, list)
+    }
+
+    fn take_notices(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notices)
+    }
+}
+
+/// A task list kept in memory, for embedding the store and for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    list: TaskList,
+}
+
+impl Storage for MemoryStorage {
+    fn load(&mut self) -> Result<TaskList> {
+        Ok(self.list.clone())
+    }
+
+    fn save(&mut self, list: &TaskList) -> Result<()> {
+        self.list = list.clone();
+        Ok(())
+    }
+}
+
+/// Compare two optional values, putting `None` after every `Some`.
+fn unset_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(texts: &[&str]) -> TaskStore<MemoryStorage> {
+        let mut store = TaskStore::open(MemoryStorage::default()).unwrap();
+        for text in texts {
+            store.add(text).unwrap();
+        }
+        store
+    }
+
+    fn ids(tasks: &[&Task]) -> Vec<usize> {
+        tasks.iter().map(|task| task.id).collect()
+    }
+
+    #[test]
+    fn memory_storage_starts_empty() {
+        let store = store(&[]);
+        assert!(store.tasks().is_empty());
+        assert_eq!(store.list.next_id, 1);
+    }
+
+    #[test]
+    fn changes_are_saved_and_load_back() {
+        let mut store = store(&[
+            "(A) Renew certificates +infra due:2026-11-01",
+            "Call the bank",
+        ]);
+        store.set_done(2, true, false).unwrap();
+        assert_eq!(store.storage.list.tasks.len(), 2);
+
+        let reopened = TaskStore::open(store.storage).unwrap();
+        let task = &reopened.tasks()[0];
+        assert_eq!(task.title, "Renew certificates +infra");
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.due, parse_date("2026-11-01").ok());
+        assert!(reopened.tasks()[1].done);
+        assert_eq!(reopened.list.next_id, 3);
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let mut store = store(&["one", "two"]);
+        store.remove(2).unwrap();
+        assert_eq!(store.add("three").unwrap().id, 3);
+        assert!(matches!(store.remove(2), Err(Error::NoSuchTask(2))));
+    }
+
+    #[test]
+    fn edit_replaces_the_whole_text() {
+        let mut store = store(&["(A) Renew certificates due:2026-11-01 every year"]);
+        let task = store.edit(1, "Renew certificates +infra").unwrap();
+        assert_eq!(task.title, "Renew certificates +infra");
+        assert_eq!((task.priority, task.due), (None, None));
+        assert!(task.recurrence.is_none());
+        let task = store
+            .edit(1, "(B) Renew certificates due:2026-12-01")
+            .unwrap();
+        assert_eq!(
+            (task.priority, task.due),
+            (Some('B'), Date::parse("2026-12-01"))
+        );
+    }
+
+    #[test]
+    fn completing_the_last_subtask_completes_the_parent() {
+        let mut store = store(&["Parent"]);
+        store.add_subtask(1, "One").unwrap();
+        store.add_subtask(1, "Two").unwrap();
+        assert!(store.set_done(2, true, false).unwrap().parents.is_empty());
+        assert_eq!(ids(&store.set_done(3, true, false).unwrap().parents), [1]);
+        assert_eq!(ids(&store.set_done(3, false, false).unwrap().parents), [1]);
+        assert!(!store.tasks()[0].done);
+    }
+
+    #[test]
+    fn done_is_refused_while_blockers_or_subtasks_are_open() {
+        let mut store = store(&["Blocker", "Task"]);
+        store.link(2, 1).unwrap();
+        store.add_subtask(2, "Subtask").unwrap();
+        let err = store.set_done(2, true, false).err().unwrap();
+        assert!(matches!(&err, Error::Blocked { id: 2, by } if by == &[1, 3]));
+        assert_eq!(err.exit_code(), 6);
+        assert!(!store.task(2).unwrap().done);
+        assert!(store.set_done(2, true, true).unwrap().task.done);
+    }
+
+    #[test]
+    fn roll_up_stops_at_a_parent_with_open_blockers() {
+        let mut store = store(&["Grandparent", "Blocker"]);
+        store.add_subtask(1, "Parent").unwrap();
+        store.add_subtask(3, "Child").unwrap();
+        store.link(3, 2).unwrap();
+        let completion = store.set_done(4, true, false).unwrap();
+        assert!(completion.parents.is_empty());
+        assert!(!store.task(3).unwrap().done);
+        assert!(!store.task(1).unwrap().done);
+        // Once the blocker is done, finishing the parent rolls up as usual.
+        store.set_done(2, true, false).unwrap();
+        assert_eq!(ids(&store.set_done(3, true, false).unwrap().parents), [1]);
+    }
+
+    #[test]
+    fn links_that_would_wait_on_themselves_are_refused() {
+        let mut store = store(&["One", "Two", "Three"]);
+        store.link(2, 1).unwrap();
+        store.link(3, 2).unwrap();
+        assert!(matches!(store.link(1, 3), Err(Error::InvalidInput(_))));
+        assert!(matches!(store.link(1, 1), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn links_between_parents_and_subtasks_are_refused() {
+        let mut store = store(&["Parent"]);
+        store.add_subtask(1, "Child").unwrap();
+        store.add_subtask(2, "Grandchild").unwrap();
+        // A parent waits on its subtasks, so they cannot wait on it.
+        let Err(Error::InvalidInput(message)) = store.link(2, 1) else {
+            panic!("linked a subtask to its parent");
+        };
+        assert!(message.ends_with("(2 -> 1 -> 2)"), "{}", message);
+        assert!(store.link(3, 1).is_err());
+        // Waiting on a subtask is what a parent does anyway.
+        store.link(1, 3).unwrap();
+    }
+
+    #[test]
+    fn parents_that_would_wait_on_themselves_are_refused() {
+        let mut store = store(&["One", "Two", "Three"]);
+        store.set_parent(2, Some(1)).unwrap();
+        store.set_parent(3, Some(2)).unwrap();
+        assert!(store.set_parent(1, Some(3)).is_err());
+        assert!(store.set_parent(1, Some(1)).is_err());
+        // A blocker cannot become the parent: it would wait on the task
+        // that waits on it.
+        store.set_parent(3, None).unwrap();
+        store.link(1, 3).unwrap();
+        let Err(Error::InvalidInput(message)) = store.set_parent(1, Some(3)) else {
+            panic!("made a blocker the parent of the task it blocks");
+        };
+        assert!(message.ends_with("(3 -> 1 -> 3)"), "{}", message);
+    }
+
+    #[test]
+    fn move_to_clamps_past_the_end() {
+        let mut store = store(&["one", "two", "three"]);
+        assert_eq!(store.move_to(1, 10).unwrap().0, 3);
+        assert_eq!(store.move_to(3, 1).unwrap().0, 1);
+        assert_eq!(ids(&store.list(&ListOptions::default())), [3, 2, 1]);
+        assert!(matches!(store.move_to(1, 0), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn list_filters_by_tag_and_sorts_unset_last() {
+        let store = store(&["(B) b +work", "plain", "(A) a +work @desk"]);
+        let options = ListOptions {
+            tags: vec!["+work".to_string()],
+            sort: SortKey::Priority,
+            ..ListOptions::default()
+        };
+        assert_eq!(ids(&store.list(&options)), [3, 1]);
+        let options = ListOptions {
+            sort: SortKey::Priority,
+            ..ListOptions::default()
+        };
+        assert_eq!(ids(&store.list(&options)), [3, 1, 2]);
+    }
+
+    #[test]
+    fn a_series_ends_past_the_last_year() {
+        let mut store = store(&["Renew the lease due:9999-06-01 every year"]);
+        let completion = store.set_done(1, true, false).unwrap();
+        assert!(completion.next.is_none());
+        assert_eq!(store.tasks().len(), 1);
+    }
+
+    #[test]
+    fn marking_a_task_as_it_is_changes_nothing() {
+        let mut store = store(&["Pay rent"]);
+        store.set_done(1, true, false).unwrap();
+        store.list.tasks[0].completed = Some(Timestamp(1_000));
+        store.list.tasks[0].modified = Some(Timestamp(2_000));
+        let task = store.set_done(1, true, false).unwrap().task;
+        assert_eq!(
+            (task.done, task.completed, task.modified),
+            (true, Some(Timestamp(1_000)), Some(Timestamp(2_000)))
+        );
+        store.set_done(1, false, false).unwrap();
+        store.list.tasks[0].modified = Some(Timestamp(3_000));
+        let task = store.set_done(1, false, false).unwrap().task;
+        assert_eq!(
+            (task.done, task.completed, task.modified),
+            (false, None, Some(Timestamp(3_000)))
+        );
+    }
+
+    fn date(text: &str) -> Option<Date> {
+        parse_date(text).ok()
+    }
+
+    #[test]
+    fn completing_a_recurring_task_adds_the_next_occurrence() {
+        let mut store = store(&["Standup +work due:2099-01-05 every mon,thu"]);
+        let completion = store.set_done(1, true, false).unwrap();
+        assert!(completion.task.done);
+        let next = completion.next.unwrap();
+        assert_eq!((next.id, next.done, next.completed), (2, false, None));
+        assert_eq!(next.title, "Standup +work");
+        assert_eq!(next.due, date("2099-01-08"));
+        assert_eq!(next.recurrence, completion.task.recurrence);
+
+        let next = store.set_done(2, true, false).unwrap().next.unwrap();
+        assert_eq!((next.id, next.due), (3, date("2099-01-12")));
+        assert_eq!(store.list.next_id, 4);
+    }
+
+    #[test]
+    fn every_month_clamps_to_the_month_end() {
+        let mut store = store(&["Pay rent due:2099-01-31 every month"]);
+        let next = store.set_done(1, true, false).unwrap().next.unwrap();
+        assert_eq!(next.due, date("2099-02-28"));
+        let next = store.set_done(2, true, false).unwrap().next.unwrap();
+        assert_eq!(next.due, date("2099-03-28"));
+    }
+
+    #[test]
+    fn an_overdue_series_comes_back_after_today() {
+        let mut store = store(&["Water the plants due:2000-01-03 every week"]);
+        let today = Timestamp::now().date();
+        let due = store
+            .set_done(1, true, false)
+            .unwrap()
+            .next
+            .unwrap()
+            .due
+            .unwrap();
+        assert!(due > today && due <= today.add_days(7), "{:?}", due);
+        assert_eq!(due.weekday(), date("2000-01-03").unwrap().weekday());
+    }
+
+    #[test]
+    fn a_recurring_subtask_keeps_its_parent_open() {
+        let mut store = store(&["Garden"]);
+        store
+            .add_subtask(1, "Water the plants due:2099-01-05 every week")
+            .unwrap();
+        let completion = store.set_done(2, true, false).unwrap();
+        assert!(completion.parents.is_empty());
+        assert_eq!(completion.next.unwrap().parent, Some(1));
+        assert!(!store.list.tasks[0].done);
+        assert_eq!(ids(&store.subtasks(1)), [2, 3]);
+    }
+
+    #[test]
+    fn due_splits_overdue_and_upcoming() {
+        let mut store = store(&[
+            "Renew the lease due:2026-10-10",
+            "Call the bank due:2026-10-25",
+            "File taxes due:2026-09-01",
+            "Pay rent due:2026-10-18",
+            "Book flights due:2026-10-26",
+            "Read a book",
+            "Send invoices due:2026-10-01",
+        ]);
+        store.set_done(7, true, false).unwrap();
+        let (overdue, upcoming) = store.due(date("2026-10-18").unwrap(), 7);
+        assert_eq!(ids(&overdue), [3, 1]);
+        assert_eq!(ids(&upcoming), [4, 2]);
+        let (overdue, upcoming) = store.due(date("2026-10-18").unwrap(), 0);
+        assert_eq!((ids(&overdue), ids(&upcoming)), (vec![3, 1], vec![4]));
+    }
+
+    #[test]
+    fn start_and_stop_open_and_close_intervals() {
+        let mut store = store(&["Write the report", "Review PRs"]);
+        assert!(store.start(1).unwrap().is_empty());
+        assert_eq!(store.tracking().map(|i| i.task).collect::<Vec<_>>(), [1]);
+        assert!(matches!(store.start(1), Err(Error::InvalidInput(_))));
+        assert!(matches!(store.start(9), Err(Error::NoSuchTask(9))));
+
+        let stopped = store.start(2).unwrap();
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0].task, 1);
+        assert!(stopped[0].end.is_some());
+        assert_eq!(store.tracking().map(|i| i.task).collect::<Vec<_>>(), [2]);
+
+        let stopped = store.stop().unwrap();
+        assert_eq!((stopped.len(), stopped[0].task), (1, 2));
+        assert_eq!(store.tracking().count(), 0);
+        assert_eq!(store.storage.list.intervals.len(), 2);
+        assert!(matches!(store.stop(), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn report_adds_up_tasks_and_tags() {
+        let mut store = store(&[
+            "Write the report +work +writing",
+            "Review PRs +work",
+            "Sort the inbox +work",
+        ]);
+        store.remove(3).unwrap();
+        let interval = |task, start, end: Option<i64>| Interval {
+            task,
+            start: Timestamp(start),
+            end: end.map(Timestamp),
+        };
+        store.list.intervals = vec![
+            interval(1, 1_000, Some(1_600)),
+            interval(3, 100, Some(400)),
+            interval(1, 2_000, Some(2_300)),
+            interval(2, 1_500, None),
+        ];
+
+        // The removed task keeps its time but credits no tags.
+        let report = store.report(None, Timestamp(2_500));
+        assert_eq!(report.tasks, [(2, 1_000), (1, 900), (3, 300)]);
+        assert_eq!(
+            report.tags,
+            [("+work".to_string(), 1_900), ("+writing".to_string(), 900)]
+        );
+
+        let report = store.report(Some(Timestamp(2_100)), Timestamp(2_500));
+        assert_eq!(report.tasks, [(2, 400), (1, 200)]);
+        assert_eq!(
+            report.tags,
+            [("+work".to_string(), 600), ("+writing".to_string(), 200)]
+        );
+    }
+}
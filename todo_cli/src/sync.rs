@@ -0,0 +1,569 @@
+//! Sharing a todo file through git; see "Git sync" in the README.
+
+use std::path::Path;
+use std::process;
+
+use crate::error::{Error, Result};
+use crate::file::parse_tasks;
+use crate::store::{FileStorage, Interval, Storage, TaskList};
+use crate::task::Task;
+
+/// Commit the todo file at `file_path` to the git repository it is in,
+/// bring in the upstream branch's changes and push, as described in the
+/// header. Holds the todo file's lock throughout and returns what was done,
+/// one line per step.
+pub fn sync(file_path: &Path) -> Result<Vec<String>> {
+    let mut storage = FileStorage::open(file_path)?;
+    // Loading upgrades an old file, so only the current format is committed.
+    let ours = storage.load()?;
+    let mut done = storage.take_notices();
+    let dir = match file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => dir,
+        None => Path::new("."),
+    };
+    let name = file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::InvalidInput(format!("Cannot sync {}", file_path.display())))?;
+    // Relative to `dir`; see gitrevisions(7).
+    let spec = |rev: &str| format!("{}:./{}", rev, name);
+
+    // A fresh clone may not have the file yet; there is nothing to commit.
+    let exists = file_path.exists();
+    if exists {
+        git(dir, &["add", "--", name])?;
+    }
+    if exists && !git_succeeds(dir, &["diff", "--cached", "--quiet", "--", name])? {
+        git(
+            dir,
+            &["commit", "--quiet", "-m", "Update todo list", "--", name],
+        )?;
+        done.push("Committed local changes".to_string());
+    }
+
+    let branch = git(dir, &["symbolic-ref", "--short", "HEAD"])?;
+    let upstream = Upstream::of(dir, &branch)?;
+    let remote = upstream.remote.as_str();
+    let remote_branch = upstream.merge.as_str();
+    let tracking = upstream.tracking.as_str();
+    let upstream = upstream.name();
+
+    if !git_succeeds(dir, &["rev-parse", "--verify", "--quiet", tracking])? {
+        git(
+            dir,
+            &[
+                "push",
+                "--quiet",
+                "--set-upstream",
+                remote,
+                &format!("HEAD:{}", remote_branch),
+            ],
+        )?;
+        done.push(format!("Pushed to new branch {}", upstream));
+        return Ok(done);
+    }
+    let born = git_succeeds(dir, &["rev-parse", "--verify", "--quiet", "HEAD"])?;
+    if !born || git_succeeds(dir, &["merge-base", "--is-ancestor", "HEAD", tracking])? {
+        if !born || git(dir, &["rev-parse", "HEAD"])? != git(dir, &["rev-parse", tracking])? {
+            git(dir, &["merge", "--quiet", "--ff-only", tracking])?;
+            done.push(format!("Fast-forwarded to {}", upstream));
+        }
+    } else {
+        if !git_succeeds(dir, &["merge-base", "--is-ancestor", tracking, "HEAD"])? {
+            // Histories started separately have no common ancestor.
+            let base = match git(dir, &["merge-base", "HEAD", tracking]) {
+                Ok(base_rev) => tasks_at(dir, &spec(&base_rev))?,
+                Err(_) => TaskList::default(),
+            };
+            let theirs = tasks_at(dir, &spec(tracking))?;
+            let (merged, renumbered) = merge_lists(&base, &ours, &theirs);
+            // Conflicts in the todo file are expected; it is replaced below.
+            // Failing to start the merge at all is not.
+            let merge = git(
+                dir,
+                &[
+                    "merge",
+                    "--quiet",
+                    "--no-commit",
+                    "--no-ff",
+                    "--allow-unrelated-histories",
+                    tracking,
+                ],
+            );
+            if let Err(err) = merge {
+                if !git_succeeds(dir, &["rev-parse", "--quiet", "--verify", "MERGE_HEAD"])? {
+                    return Err(err);
+                }
+            }
+            storage.save(&merged)?;
+            git(dir, &["add", "--", name])?;
+            let conflicts = git(dir, &["diff", "--name-only", "--diff-filter=U"])?;
+            if !conflicts.is_empty() {
+                git(dir, &["merge", "--abort"])?;
+                storage.save(&ours)?;
+                return Err(Error::Git {
+                    command: format!("merge {}", upstream),
+                    message: format!(
+                        "conflicts in {}; merge it with git, then sync again",
+                        conflicts.lines().collect::<Vec<_>>().join(", ")
+                    ),
+                });
+            }
+            git(dir, &["commit", "--quiet", "--no-edit"])?;
+            done.extend(renumbered);
+            done.push(format!("Merged {}", upstream));
+        }
+        git(
+            dir,
+            &[
+                "push",
+                "--quiet",
+                remote,
+                &format!("HEAD:{}", remote_branch),
+            ],
+        )?;
+        done.push(format!("Pushed to {}", upstream));
+    }
+    if done.is_empty() {
+        done.push("Already up to date".to_string());
+    }
+    Ok(done)
+}
+
+/// Where a branch pulls from and pushes to.
+struct Upstream {
+    /// The remote, or "." for a branch of the same repository.
+    remote: String,
+    /// The branch on the remote, like `refs/heads/main`.
+    merge: String,
+    /// The local ref holding the remote branch as last fetched.
+    tracking: String,
+}
+
+impl Upstream {
+    /// The upstream configured for `branch`, or `<branch>` on `origin` when
+    /// none is. Fetches the remote, so that `tracking` is up to date.
+    fn of(dir: &Path, branch: &str) -> Result<Upstream> {
+        let config = |key: &str| {
+            let key = format!("branch.{}.{}", branch, key);
+            git(dir, &["config", "--get", &key]).ok()
+        };
+        let configured = config("remote").zip(config("merge"));
+        let (remote, merge) = configured
+            .clone()
+            .unwrap_or_else(|| ("origin".to_string(), format!("refs/heads/{}", branch)));
+        if remote == "." {
+            return Ok(Upstream {
+                tracking: merge.clone(),
+                remote,
+                merge,
+            });
+        }
+        git(dir, &["fetch", "--quiet", &remote])?;
+        // git maps the branch to its remote-tracking ref through the
+        // remote's fetch refspec; without an upstream, assume the default.
+        let resolved = match configured {
+            Some(_) => git(dir, &["rev-parse", "--symbolic-full-name", "@{upstream}"]).ok(),
+            None => None,
+        };
+        let tracking = resolved.filter(|name| !name.is_empty()).unwrap_or_else(|| {
+            let name = merge.strip_prefix("refs/heads/").unwrap_or(&merge);
+            format!("refs/remotes/{}/{}", remote, name)
+        });
+        Ok(Upstream {
+            remote,
+            merge,
+            tracking,
+        })
+    }
+
+    /// The short name of the upstream branch, like `origin/main`, for
+    /// messages.
+    fn name(&self) -> String {
+        let tracking = self.tracking.as_str();
+        tracking
+            .strip_prefix("refs/remotes/")
+            .or_else(|| tracking.strip_prefix("refs/heads/"))
+            .unwrap_or(tracking)
+            .to_string()
+    }
+}
+
+/// The tasks in the todo file at a revision spec like `HEAD:./todo.txt`, or
+/// an empty list when the file does not exist there.
+fn tasks_at(dir: &Path, spec: &str) -> Result<TaskList> {
+    if !git_succeeds(dir, &["cat-file", "-e", spec])? {
+        return Ok(TaskList::default());
+    }
+    let contents = git(dir, &["show", spec])?;
+    parse_tasks(Path::new(spec), &contents).map(|(list, _)| list)
+}
+
+/// Run git in `dir`, returning its output without the trailing newline.
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = git_command(dir, args)
+        .output()
+        .map_err(|e| Error::io("git", e))?;
+    if !output.status.success() {
+        return Err(Error::Git {
+            command: args.join(" "),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Run git in `dir` for a yes-or-no answer given by its exit status.
+fn git_succeeds(dir: &Path, args: &[&str]) -> Result<bool> {
+    let output = git_command(dir, args)
+        .output()
+        .map_err(|e| Error::io("git", e))?;
+    Ok(output.status.success())
+}
+
+fn git_command(dir: &Path, args: &[&str]) -> process::Command {
+    let mut command = process::Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(process::Stdio::null());
+    command
+}
+
+/// Three-way merge of two versions of a list that share the ancestor
+/// `base`, task by task on stable ids. See "Git sync" in the header for the
+/// rules. Returns the merged list and one line per local task that had to
+/// take a new id.
+pub fn merge_lists(base: &TaskList, ours: &TaskList, theirs: &TaskList) -> (TaskList, Vec<String>) {
+    let find = |list: &TaskList, id| {
+        list.tasks
+            .iter()
+            .find(|task: &&Task| task.id == id)
+            .cloned()
+    };
+    let mut next_id = [base, ours, theirs]
+        .iter()
+        .flat_map(|list| {
+            list.tasks
+                .iter()
+                .map(|task| task.id + 1)
+                .chain([list.next_id])
+        })
+        .max()
+        .unwrap_or(1);
+
+    // Different tasks added on both sides under one id: the remote one
+    // keeps it, and local references follow the local one.
+    let mut ours = ours.clone();
+    let mut renumbered = Vec::new();
+    let mut notices = Vec::new();
+    for task in ours.tasks.iter_mut() {
+        let clash = find(base, task.id).is_none()
+            && find(theirs, task.id).is_some_and(|other| other != *task);
+        if clash {
+            notices.push(format!(
+                "Task \"{}\" shared id {} with a remote task; it is now task {}",
+                task.title, task.id, next_id
+            ));
+            renumbered.push((task.id, next_id));
+            task.id = next_id;
+            next_id += 1;
+        }
+    }
+    let renumber = |id: usize| {
+        renumbered
+            .iter()
+            .find(|&&(old, _)| old == id)
+            .map_or(id, |&(_, new)| new)
+    };
+    for task in ours.tasks.iter_mut() {
+        task.parent = task.parent.map(renumber);
+        task.blocked_by = task.blocked_by.iter().map(|&id| renumber(id)).collect();
+    }
+    for interval in ours.intervals.iter_mut() {
+        if find(base, interval.task).is_none() {
+            interval.task = renumber(interval.task);
+        }
+    }
+
+    // Tracked time from both sides; an interval stopped on one side is
+    // stopped in the result.
+    let mut intervals = theirs.intervals.clone();
+    for interval in &ours.intervals {
+        let same =
+            |other: &&mut Interval| other.task == interval.task && other.start == interval.start;
+        match intervals.iter_mut().find(same) {
+            Some(other) => other.end = other.end.max(interval.end),
+            None => intervals.push(interval.clone()),
+        }
+    }
+    intervals.sort_by_key(|interval| interval.start);
+
+    // Follow the remote order unless only this side reordered tasks.
+    let order = |list: &TaskList| -> Vec<usize> {
+        list.tasks
+            .iter()
+            .map(|task| task.id)
+            .filter(|&id| find(base, id).is_some())
+            .collect()
+    };
+    let base_order: Vec<usize> = base.tasks.iter().map(|task| task.id).collect();
+    let kept_order = |list: &TaskList| -> Vec<usize> {
+        let order = order(list);
+        base_order
+            .iter()
+            .copied()
+            .filter(|id| order.contains(id))
+            .collect()
+    };
+    let (first, second) =
+        if order(theirs) == kept_order(theirs) && order(&ours) != kept_order(&ours) {
+            (&ours, theirs)
+        } else {
+            (theirs, &ours)
+        };
+
+    let mut tasks: Vec<Task> = Vec::new();
+    for task in first.tasks.iter().chain(&second.tasks) {
+        if tasks.iter().any(|t| t.id == task.id) {
+            continue;
+        }
+        let base_task = find(base, task.id);
+        match (base_task, find(&ours, task.id), find(theirs, task.id)) {
+            (Some(base_task), Some(ours), Some(theirs)) => {
+                tasks.push(merge_task(&base_task, &ours, &theirs));
+            }
+            // Removed on one side: keep it only if the other side changed it.
+            (Some(base_task), Some(kept), None) | (Some(base_task), None, Some(kept)) => {
+                if kept != base_task {
+                    tasks.push(kept);
+                }
+            }
+            // Added on one side, or identically on both.
+            (None, Some(added), _) | (None, None, Some(added)) => tasks.push(added),
+            (_, None, None) => {}
+        }
+    }
+    let merged = TaskList {
+        next_id,
+        tasks,
+        intervals,
+    };
+    (merged, notices)
+}
+
+/// Merge two versions of one task field by field. A field changed on both
+/// sides takes the value of the task modified last, the remote on a tie.
+fn merge_task(base: &Task, ours: &Task, theirs: &Task) -> Task {
+    let ours_newer = ours.modified > theirs.modified;
+    fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T, ours_newer: bool) -> T {
+        if ours == theirs || base == theirs || (base != ours && ours_newer) {
+            ours.clone()
+        } else {
+            theirs.clone()
+        }
+    }
+    // done and completed change together.
+    let (done, completed) = pick(
+        &(base.done, base.completed),
+        &(ours.done, ours.completed),
+        &(theirs.done, theirs.completed),
+        ours_newer,
+    );
+    Task {
+        id: theirs.id,
+        title: pick(&base.title, &ours.title, &theirs.title, ours_newer),
+        done,
+        priority: pick(&base.priority, &ours.priority, &theirs.priority, ours_newer),
+        due: pick(&base.due, &ours.due, &theirs.due, ours_newer),
+        created: pick(&base.created, &ours.created, &theirs.created, ours_newer),
+        completed,
+        recurrence: pick(
+            &base.recurrence,
+            &ours.recurrence,
+            &theirs.recurrence,
+            ours_newer,
+        ),
+        modified: ours.modified.max(theirs.modified),
+        parent: pick(&base.parent, &ours.parent, &theirs.parent, ours_newer),
+        blocked_by: pick(
+            &base.blocked_by,
+            &ours.blocked_by,
+            &theirs.blocked_by,
+            ours_newer,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::Timestamp;
+
+    /// A task last modified at second `modified`.
+    fn task(id: usize, title: &str, modified: i64) -> Task {
+        let mut task = Task::from_todo_txt(title).unwrap();
+        task.id = id;
+        task.modified = Some(Timestamp(modified));
+        task
+    }
+
+    fn list(tasks: Vec<Task>) -> TaskList {
+        TaskList {
+            next_id: tasks.iter().map(|task| task.id + 1).max().unwrap_or(1),
+            tasks,
+            intervals: Vec::new(),
+        }
+    }
+
+    fn titles(list: &TaskList) -> Vec<(usize, &str)> {
+        list.tasks
+            .iter()
+            .map(|task| (task.id, task.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn fields_changed_on_one_side_are_both_kept() {
+        let base = list(vec![task(1, "Pay rent", 0)]);
+        let ours = list(vec![task(1, "(A) Pay rent", 10)]);
+        let theirs = list(vec![task(1, "Pay rent due:2026-11-01", 5)]);
+        let (merged, _) = merge_lists(&base, &ours, &theirs);
+        let task = &merged.tasks[0];
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.due, crate::Date::parse("2026-11-01"));
+        assert_eq!(task.modified, Some(Timestamp(10)));
+    }
+
+    #[test]
+    fn a_field_changed_on_both_sides_takes_the_newer_value() {
+        let base = list(vec![task(1, "Pay rent", 0)]);
+        let ours = list(vec![task(1, "Pay the rent", 10)]);
+        let theirs = list(vec![task(1, "Pay rent today", 20)]);
+        assert_eq!(
+            titles(&merge_lists(&base, &ours, &theirs).0),
+            [(1, "Pay rent today")]
+        );
+        let theirs = list(vec![task(1, "Pay rent today", 5)]);
+        assert_eq!(
+            titles(&merge_lists(&base, &ours, &theirs).0),
+            [(1, "Pay the rent")]
+        );
+        // On a tie the remote wins.
+        let theirs = list(vec![task(1, "Pay rent today", 10)]);
+        assert_eq!(
+            titles(&merge_lists(&base, &ours, &theirs).0),
+            [(1, "Pay rent today")]
+        );
+    }
+
+    #[test]
+    fn id_clashes_renumber_the_local_task() {
+        let base = list(vec![task(1, "Shared", 0)]);
+        let mut ours = list(vec![
+            task(1, "Shared", 0),
+            task(2, "Local", 10),
+            task(3, "Child", 10),
+        ]);
+        ours.tasks[2].parent = Some(2);
+        ours.tasks[0].blocked_by = vec![2];
+        ours.intervals.push(Interval {
+            task: 2,
+            start: Timestamp(100),
+            end: Some(Timestamp(200)),
+        });
+        let theirs = list(vec![task(1, "Shared", 0), task(2, "Remote", 10)]);
+        let (merged, notices) = merge_lists(&base, &ours, &theirs);
+        assert_eq!(
+            titles(&merged),
+            [(1, "Shared"), (2, "Remote"), (4, "Local"), (3, "Child")]
+        );
+        assert_eq!(
+            notices,
+            ["Task \"Local\" shared id 2 with a remote task; it is now task 4"]
+        );
+        // References to the local task follow it to its new id.
+        assert_eq!(merged.tasks[3].parent, Some(4));
+        assert_eq!(merged.tasks[0].blocked_by, [4]);
+        assert_eq!(merged.intervals[0].task, 4);
+        assert_eq!(merged.next_id, 5);
+    }
+
+    #[test]
+    fn the_same_task_added_on_both_sides_is_kept_once() {
+        let base = list(Vec::new());
+        let ours = list(vec![task(1, "Same", 10)]);
+        let (merged, _) = merge_lists(&base, &ours, &ours.clone());
+        assert_eq!(titles(&merged), [(1, "Same")]);
+    }
+
+    #[test]
+    fn the_order_follows_the_side_that_reordered() {
+        let base = list(vec![
+            task(1, "One", 0),
+            task(2, "Two", 0),
+            task(3, "Three", 0),
+        ]);
+        let reordered = list(vec![
+            task(3, "Three", 0),
+            task(1, "One", 0),
+            task(2, "Two", 0),
+        ]);
+        let mut added = base.clone();
+        added.tasks.push(task(4, "Four", 10));
+        let (merged, _) = merge_lists(&base, &reordered, &added);
+        assert_eq!(
+            titles(&merged),
+            [(3, "Three"), (1, "One"), (2, "Two"), (4, "Four")]
+        );
+        let (merged, _) = merge_lists(&base, &added, &reordered);
+        assert_eq!(
+            titles(&merged),
+            [(3, "Three"), (1, "One"), (2, "Two"), (4, "Four")]
+        );
+    }
+
+    #[test]
+    fn an_edit_beats_a_removal() {
+        let base = list(vec![task(1, "Kept", 0), task(2, "Gone", 0)]);
+        let ours = list(vec![task(1, "Kept", 0)]);
+        let theirs = list(vec![task(1, "Kept", 0), task(2, "Gone but edited", 10)]);
+        assert_eq!(
+            titles(&merge_lists(&base, &ours, &theirs).0),
+            [(1, "Kept"), (2, "Gone but edited")]
+        );
+        assert_eq!(
+            titles(&merge_lists(&base, &theirs, &ours).0),
+            [(1, "Kept"), (2, "Gone but edited")]
+        );
+        // A task removed on one side and untouched on the other goes.
+        let theirs = base.clone();
+        assert_eq!(titles(&merge_lists(&base, &ours, &theirs).0), [(1, "Kept")]);
+    }
+
+    #[test]
+    fn tracked_time_from_both_sides_is_kept() {
+        let base = list(vec![task(1, "Work", 0)]);
+        let interval = |start: i64, end: Option<i64>| Interval {
+            task: 1,
+            start: Timestamp(start),
+            end: end.map(Timestamp),
+        };
+        let mut ours = base.clone();
+        ours.intervals = vec![interval(100, Some(200)), interval(300, Some(400))];
+        let mut theirs = base.clone();
+        theirs.intervals = vec![interval(50, Some(60)), interval(300, None)];
+        let (merged, _) = merge_lists(&base, &ours, &theirs);
+        assert_eq!(
+            merged.intervals,
+            [
+                interval(50, Some(60)),
+                interval(100, Some(200)),
+                interval(300, Some(400))
+            ]
+        );
+    }
+}
@@ -0,0 +1,816 @@
+//! Tasks and the dates, times and recurrence rules they carry.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub id: usize,
+    pub title: String,
+    pub done: bool,
+    /// Priority letter; `A` is the most urgent.
+    pub priority: Option<char>,
+    pub due: Option<Date>,
+    pub created: Option<Timestamp>,
+    pub completed: Option<Timestamp>,
+    pub recurrence: Option<Recurrence>,
+    /// When the task was last added, edited or marked done or not done.
+    /// `sync` uses it to pick between conflicting changes.
+    pub modified: Option<Timestamp>,
+    /// The task this is a subtask of.
+    pub parent: Option<usize>,
+    /// Tasks that must be done before this one.
+    pub blocked_by: Vec<usize>,
+}
+
+impl Task {
+    /// Set the title from todo.txt-style text, taking a leading priority, a
+    /// `due:` date and a recurrence rule out of it. Ones the text does not
+    /// mention are cleared, so text from `Task::text` gives the task back.
+    pub(crate) fn apply_text(&mut self, text: &str) -> Result<()> {
+        let (priority, text) = take_priority(text.trim());
+        self.priority = priority;
+        self.due = None;
+        self.recurrence = None;
+        let all: Vec<&str> = text.split(' ').collect();
+        let mut words = Vec::new();
+        let mut i = 0;
+        while i < all.len() {
+            let word = all[i];
+            i += 1;
+            if let Some(date) = word.strip_prefix("due:") {
+                self.due = Some(Date::parse(date).ok_or_else(|| {
+                    Error::InvalidInput(format!("Invalid due date \"{}\"", date))
+                })?);
+            } else if let Some(rule) = word.strip_prefix("rec:") {
+                self.recurrence = Some(Recurrence::from_rec(rule).map_err(|msg| {
+                    Error::InvalidInput(format!("Invalid rec \"{}\": {}", rule, msg))
+                })?);
+            } else if let Some(rule) = word.strip_prefix("rrule:") {
+                self.recurrence = Some(Recurrence::from_rrule(rule).map_err(|msg| {
+                    Error::InvalidInput(format!("Invalid rrule \"{}\": {}", rule, msg))
+                })?);
+            } else if let Some((rule, used)) = Recurrence::parse_every(&all[i - 1..]) {
+                self.recurrence = Some(rule);
+                i += used - 1;
+            } else {
+                words.push(word);
+            }
+        }
+        self.title = words.join(" ");
+        Ok(())
+    }
+
+    /// The task as text that `apply_text` reads back: "(A) title due:...
+    /// every monday".
+    pub fn text(&self) -> String {
+        let mut text = self
+            .priority
+            .map(|p| format!("({}) ", p))
+            .unwrap_or_default();
+        text.push_str(&self.title);
+        if let Some(due) = self.due {
+            text.push_str(&format!(" due:{}", due));
+        }
+        if let Some(recurrence) = &self.recurrence {
+            text.push_str(&format!(" {}", recurrence));
+        }
+        text
+    }
+
+    /// The task as one todo.txt line.
+    pub fn to_todo_txt(&self) -> String {
+        let mut parts = Vec::new();
+        let priority = self.priority.map(|p| format!("({})", p));
+        if self.done {
+            parts.push("x".to_string());
+            // The creation date may only follow a completion date.
+            if let Some(completed) = self.completed {
+                parts.push(completed.date().to_string());
+                parts.extend(self.created.map(|c| c.date().to_string()));
+            }
+            parts.extend(priority);
+        } else {
+            parts.extend(priority);
+            parts.extend(self.created.map(|c| c.date().to_string()));
+        }
+        parts.push(self.title.replace(['\r', '\n'], " "));
+        parts.extend(self.due.map(|d| format!("due:{}", d)));
+        parts.extend(self.recurrence.as_ref().map(|r| match r.to_rec() {
+            Some(rec) => format!("rec:{}", rec),
+            None => format!("rrule:{}", r.to_rrule()),
+        }));
+        parts.join(" ")
+    }
+
+    /// Parse one todo.txt line into a task with id 0. The priority may come
+    /// before or after the dates.
+    pub fn from_todo_txt(line: &str) -> std::result::Result<Task, String> {
+        let mut task = Task {
+            id: 0,
+            title: String::new(),
+            done: false,
+            priority: None,
+            due: None,
+            created: None,
+            completed: None,
+            recurrence: None,
+            modified: None,
+            parent: None,
+            blocked_by: Vec::new(),
+        };
+        let mut rest = line.trim();
+        if let Some(after) = rest.strip_prefix("x ") {
+            task.done = true;
+            rest = after.trim_start();
+            if let Some((date, after)) = take_date(rest) {
+                task.completed = Some(date.start());
+                rest = after;
+            }
+        }
+        let (priority, after) = take_priority(rest);
+        rest = after;
+        if let Some((date, after)) = take_date(rest) {
+            task.created = Some(date.start());
+            rest = after;
+        }
+        task.apply_text(rest).map_err(|e| e.to_string())?;
+        task.priority = task.priority.or(priority);
+        Ok(task)
+    }
+
+    /// The `+project` and `@context` words of the title.
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.title
+            .split_whitespace()
+            .filter(|word| word.len() > 1 && (word.starts_with('+') || word.starts_with('@')))
+    }
+}
+
+/// A calendar date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// The last year `parse` accepts, and so the last a todo file can hold.
+    pub const LAST_YEAR: i64 = 9999;
+
+    /// Parse `YYYY-MM-DD` with a year from 0 to 9999, rejecting dates that
+    /// do not exist.
+    pub fn parse(text: &str) -> Option<Date> {
+        let mut parts = text.splitn(3, '-');
+        let date = Date {
+            year: parts.next()?.parse().ok()?,
+            month: parts.next()?.parse().ok()?,
+            day: parts.next()?.parse().ok()?,
+        };
+        // Bounding the year keeps `days` from overflowing.
+        if !(0..=Date::LAST_YEAR).contains(&date.year)
+            || !(1..=12).contains(&date.month)
+            || !(1..=days_in_month(date.year, date.month)).contains(&date.day)
+        {
+            return None;
+        }
+        Some(date)
+    }
+
+    /// Today's date in UTC.
+    pub fn today() -> Date {
+        Timestamp::now().date()
+    }
+
+    pub fn add_days(self, days: i64) -> Date {
+        Date::from_days(self.days() + days)
+    }
+
+    /// The same day `months` months later, or the last day of that month
+    /// when it is shorter.
+    pub fn add_months(self, months: i64) -> Date {
+        let index = self.year * 12 + self.month as i64 - 1 + months;
+        let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+        let day = self.day.min(days_in_month(year, month));
+        Date { year, month, day }
+    }
+
+    /// Day of the week, 0 being Monday.
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a Thursday.
+        (self.days() + 3).rem_euclid(7) as u32
+    }
+
+    /// Midnight UTC at the start of the date.
+    pub fn start(self) -> Timestamp {
+        Timestamp(self.days() * 86_400)
+    }
+
+    /// Days since 1970-01-01.
+    fn days(self) -> i64 {
+        // Shift the year to start in March so the leap day comes last.
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month_index = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month_index + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// The date `days` days after 1970-01-01.
+    fn from_days(days: i64) -> Date {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+}
+
+/// Number of days in `month` of `year`, in the proleptic Gregorian calendar.
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A point in time, in whole seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(pub i64);
+
+impl Timestamp {
+    pub fn now() -> Timestamp {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Timestamp(since_epoch.as_secs() as i64)
+    }
+
+    pub fn date(self) -> Date {
+        Date::from_days(self.0.div_euclid(86_400))
+    }
+
+    /// Parse the `YYYY-MM-DDTHH:MM:SSZ` form that `Display` writes.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let (date, time) = text.strip_suffix('Z')?.split_once('T')?;
+        let date = Date::parse(date)?;
+        let mut parts = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+        let (hour, minute, second) = (parts.next()??, parts.next()??, parts.next()??);
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        Some(Timestamp(
+            date.days() * 86_400 + hour * 3600 + minute * 60 + second,
+        ))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.0.rem_euclid(86_400);
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}Z",
+            self.date(),
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+/// How often a recurring task comes back. This is the subset of RFC 5545
+/// recurrence rules that `every ...` can express.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Recur every `interval` days, weeks, months or years.
+    pub interval: u32,
+    /// For weekly rules, the days of the week it falls on (0 is Monday);
+    /// empty means the weekday of the due date.
+    pub weekdays: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Weekday names as `every` and RRULE's BYDAY spell them, Monday first.
+const WEEKDAYS: [(&str, &str); 7] = [
+    ("monday", "MO"),
+    ("tuesday", "TU"),
+    ("wednesday", "WE"),
+    ("thursday", "TH"),
+    ("friday", "FR"),
+    ("saturday", "SA"),
+    ("sunday", "SU"),
+];
+
+impl Recurrence {
+    fn new(frequency: Frequency, interval: u32) -> Recurrence {
+        Recurrence {
+            frequency,
+            interval,
+            weekdays: Vec::new(),
+        }
+    }
+
+    /// Parse `every <rule>` at the start of `words`, returning the rule and
+    /// how many words it took. Anything else is not a rule and stays part of
+    /// the title.
+    fn parse_every(words: &[&str]) -> Option<(Recurrence, usize)> {
+        if words.first()?.to_lowercase() != "every" {
+            return None;
+        }
+        let word = words.get(1)?.to_lowercase();
+        let unit = |name: &str| match name.trim_end_matches('s') {
+            "d" | "day" => Some(Frequency::Daily),
+            "w" | "week" => Some(Frequency::Weekly),
+            "m" | "month" => Some(Frequency::Monthly),
+            "y" | "year" => Some(Frequency::Yearly),
+            _ => None,
+        };
+        // "every 3d" and "every 3 days".
+        let digits = word.chars().take_while(char::is_ascii_digit).count();
+        if digits > 0 {
+            let interval: u32 = word[..digits].parse().ok().filter(|&n| n > 0)?;
+            if digits < word.len() {
+                return Some((Recurrence::new(unit(&word[digits..])?, interval), 2));
+            }
+            let frequency = unit(&words.get(2)?.to_lowercase())?;
+            return Some((Recurrence::new(frequency, interval), 3));
+        }
+        if word == "day" || word == "week" || word == "month" || word == "year" {
+            return Some((Recurrence::new(unit(&word)?, 1), 2));
+        }
+        let weekdays = if word == "weekday" || word == "weekdays" {
+            vec![0, 1, 2, 3, 4]
+        } else {
+            // "monday", "mon" and lists like "mon,thu".
+            let mut days = Vec::new();
+            for name in word.split(',') {
+                let day = WEEKDAYS
+                    .iter()
+                    .position(|(full, _)| name.len() >= 3 && full.starts_with(name))?;
+                days.push(day as u32);
+            }
+            days.sort_unstable();
+            days.dedup();
+            days
+        };
+        Some((
+            Recurrence {
+                frequency: Frequency::Weekly,
+                interval: 1,
+                weekdays,
+            },
+            2,
+        ))
+    }
+
+    /// Parse the value of an RRULE, like `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO`.
+    pub fn from_rrule(rule: &str) -> std::result::Result<Recurrence, String> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut weekdays = Vec::new();
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got \"{}\"", part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported FREQ \"{}\"", value)),
+                    });
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or_else(|| format!("invalid INTERVAL \"{}\"", value))?;
+                }
+                "BYDAY" => {
+                    for code in value.split(',') {
+                        let day = WEEKDAYS
+                            .iter()
+                            .position(|(_, short)| short.eq_ignore_ascii_case(code))
+                            .ok_or_else(|| format!("invalid BYDAY \"{}\"", code))?;
+                        weekdays.push(day as u32);
+                    }
+                }
+                _ => return Err(format!("unsupported part \"{}\"", key)),
+            }
+        }
+        let frequency = frequency.ok_or("FREQ is required")?;
+        if !weekdays.is_empty() && frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".to_string());
+        }
+        weekdays.sort_unstable();
+        weekdays.dedup();
+        Ok(Recurrence {
+            frequency,
+            interval,
+            weekdays,
+        })
+    }
+
+    /// Parse the value of a todo.txt `rec:` tag, like `2w` or `+1m`. The
+    /// `+` is accepted and ignored, as rules always count from the due
+    /// date; `b` (business days) is only supported as `1b`.
+    fn from_rec(rule: &str) -> std::result::Result<Recurrence, String> {
+        let rule = rule.strip_prefix('+').unwrap_or(rule);
+        let digits = rule.chars().take_while(char::is_ascii_digit).count();
+        let interval = match digits {
+            0 => 1,
+            _ => rule[..digits]
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or("invalid interval")?,
+        };
+        let frequency = match &rule[digits..] {
+            "d" => Frequency::Daily,
+            "w" => Frequency::Weekly,
+            "m" => Frequency::Monthly,
+            "y" => Frequency::Yearly,
+            "b" if interval == 1 => {
+                return Ok(Recurrence {
+                    frequency: Frequency::Weekly,
+                    interval,
+                    weekdays: vec![0, 1, 2, 3, 4],
+                });
+            }
+            _ => return Err("expected a count and d, w, m, y or 1b".to_string()),
+        };
+        Ok(Recurrence::new(frequency, interval))
+    }
+
+    /// The rule as the value of a todo.txt `rec:` tag, when it can say it.
+    fn to_rec(&self) -> Option<String> {
+        let unit = match self.frequency {
+            Frequency::Daily => 'd',
+            Frequency::Weekly => 'w',
+            Frequency::Monthly => 'm',
+            Frequency::Yearly => 'y',
+        };
+        match (self.interval, self.weekdays.as_slice()) {
+            (n, []) => Some(format!("{}{}", n, unit)),
+            (1, [0, 1, 2, 3, 4]) => Some("1b".to_string()),
+            _ => None,
+        }
+    }
+
+    /// The rule in RRULE form, as stored in the todo file.
+    pub fn to_rrule(&self) -> String {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        let mut rule = format!("FREQ={}", frequency);
+        if self.interval != 1 {
+            rule.push_str(&format!(";INTERVAL={}", self.interval));
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self
+                .weekdays
+                .iter()
+                .map(|&d| WEEKDAYS[d as usize].1)
+                .collect();
+            rule.push_str(&format!(";BYDAY={}", days.join(",")));
+        }
+        rule
+    }
+
+    /// The first occurrence on or after `today`, for tasks added without a
+    /// due date.
+    pub(crate) fn first(&self, today: Date) -> Date {
+        (0..7)
+            .map(|days| today.add_days(days))
+            .find(|date| self.weekdays.is_empty() || self.weekdays.contains(&date.weekday()))
+            .unwrap_or(today)
+    }
+
+    /// The first occurrence after `date`.
+    fn next(&self, date: Date) -> Date {
+        let interval = self.interval as i64;
+        match self.frequency {
+            Frequency::Daily => date.add_days(interval),
+            Frequency::Weekly if self.weekdays.is_empty() => date.add_days(7 * interval),
+            Frequency::Weekly => {
+                // A later day in the same week, else the first day in the
+                // week `interval` weeks on.
+                let weekday = date.weekday();
+                match self.weekdays.iter().find(|&&day| day > weekday) {
+                    Some(&day) => date.add_days((day - weekday) as i64),
+                    None => {
+                        let monday = date.add_days(-(weekday as i64) + 7 * interval);
+                        monday.add_days(self.weekdays[0] as i64)
+                    }
+                }
+            }
+            Frequency::Monthly => date.add_months(interval),
+            Frequency::Yearly => date.add_months(12 * interval),
+        }
+    }
+
+    /// The next occurrence after both `due` and `today`. Monthly and yearly
+    /// rules count from `due`, so the 31st comes back as the 31st wherever
+    /// a month allows it.
+    pub(crate) fn next_after(&self, due: Date, today: Date) -> Date {
+        let months = match self.frequency {
+            Frequency::Monthly => self.interval as i64,
+            Frequency::Yearly => 12 * self.interval as i64,
+            _ => {
+                let mut next = self.next(due);
+                while next <= today {
+                    next = self.next(next);
+                }
+                return next;
+            }
+        };
+        (1..)
+            .map(|n| due.add_months(n * months))
+            .find(|&next| next > today)
+            .unwrap_or(due)
+    }
+}
+
+/// The rule as `every ...` text, or as `rrule:...` when that cannot say it.
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match self.frequency {
+            Frequency::Daily => ("day", 'd'),
+            Frequency::Weekly => ("week", 'w'),
+            Frequency::Monthly => ("month", 'm'),
+            Frequency::Yearly => ("year", 'y'),
+        };
+        match (self.interval, self.weekdays.as_slice()) {
+            (1, []) => write!(f, "every {}", unit.0),
+            (n, []) => write!(f, "every {}{}", n, unit.1),
+            (1, [0, 1, 2, 3, 4]) => write!(f, "every weekday"),
+            (1, [day]) => write!(f, "every {}", WEEKDAYS[*day as usize].0),
+            (1, days) => {
+                let names: Vec<&str> = days.iter().map(|&d| &WEEKDAYS[d as usize].0[..3]).collect();
+                write!(f, "every {}", names.join(","))
+            }
+            _ => write!(f, "rrule:{}", self.to_rrule()),
+        }
+    }
+}
+
+/// Split a leading `(A)` to `(Z)` priority off `text`.
+fn take_priority(text: &str) -> (Option<char>, &str) {
+    let bytes = text.as_bytes();
+    if bytes.len() >= 3 && bytes[0] == b'(' && bytes[1].is_ascii_uppercase() && bytes[2] == b')' {
+        (Some(bytes[1] as char), text[3..].trim_start())
+    } else {
+        (None, text)
+    }
+}
+
+/// Split a leading `YYYY-MM-DD` word off `text`.
+fn take_date(text: &str) -> Option<(Date, &str)> {
+    let (word, rest) = text.split_once(' ').unwrap_or((text, ""));
+    Some((Date::parse(word)?, rest.trim_start()))
+}
+
+/// Parse a `YYYY-MM-DD` command-line value.
+pub fn parse_date(text: &str) -> std::result::Result<Date, String> {
+    Date::parse(text).ok_or_else(|| "expected a date like 2026-11-01".to_string())
+}
+
+/// Task ids separated by `separator`.
+pub fn join_ids(ids: &[usize], separator: &str) -> String {
+    ids.iter()
+        .map(usize::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(text: &str) -> Task {
+        Task::from_todo_txt(text).unwrap()
+    }
+
+    #[test]
+    fn apply_text_reads_priority_due_and_recurrence() {
+        let task = task("(B) Water plants @home due:2026-11-02 every 2w");
+        assert_eq!(task.title, "Water plants @home");
+        assert_eq!(task.priority, Some('B'));
+        assert_eq!(task.due, Date::parse("2026-11-02"));
+        assert_eq!(task.recurrence.unwrap().to_string(), "every 2w");
+    }
+
+    #[test]
+    fn apply_text_clears_what_the_text_leaves_out() {
+        let mut task = task("(B) Water plants due:2026-11-02 every week");
+        task.apply_text("Water the plants").unwrap();
+        assert_eq!(task.title, "Water the plants");
+        assert_eq!(task.priority, None);
+        assert_eq!(task.due, None);
+        assert_eq!(task.recurrence, None);
+    }
+
+    #[test]
+    fn apply_text_reads_back_text() {
+        let original = task("(C) Pay rent +home due:2026-12-01 every month");
+        let mut copy = task("(A) Something else due:2027-01-01 every day");
+        copy.apply_text(&original.text()).unwrap();
+        assert_eq!(copy, original);
+    }
+
+    fn round_trip(line: &str) -> String {
+        task(line).to_todo_txt()
+    }
+
+    #[test]
+    fn todo_txt_priority_and_created_date() {
+        let task = task("(A) 2026-10-01 Renew TLS certificates +infra");
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(
+            task.created,
+            Some(Date::parse("2026-10-01").unwrap().start())
+        );
+        assert_eq!(task.title, "Renew TLS certificates +infra");
+        assert!(!task.done);
+        assert_eq!(
+            task.to_todo_txt(),
+            "(A) 2026-10-01 Renew TLS certificates +infra"
+        );
+    }
+
+    #[test]
+    fn todo_txt_done_task_dates() {
+        let line = "x 2026-10-18 2026-10-01 (A) Call the bank @phone";
+        let task = task(line);
+        assert!(task.done);
+        assert_eq!(
+            task.completed,
+            Some(Date::parse("2026-10-18").unwrap().start())
+        );
+        assert_eq!(
+            task.created,
+            Some(Date::parse("2026-10-01").unwrap().start())
+        );
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.to_todo_txt(), line);
+        // The priority may also come before the dates.
+        assert_eq!(
+            round_trip("x 2026-10-18 (A) 2026-10-01 Call the bank"),
+            "x 2026-10-18 2026-10-01 (A) Call the bank"
+        );
+        // A creation date is only written after a completion date.
+        assert_eq!(round_trip("x Call the bank"), "x Call the bank");
+    }
+
+    #[test]
+    fn todo_txt_due_date() {
+        let task = task("Renew TLS certificates due:2026-11-01 +infra");
+        assert_eq!(task.due, Date::parse("2026-11-01"));
+        assert_eq!(
+            task.to_todo_txt(),
+            "Renew TLS certificates +infra due:2026-11-01"
+        );
+        assert!(Task::from_todo_txt("Renew due:2026-13-01").is_err());
+    }
+
+    #[test]
+    fn todo_txt_rec() {
+        for line in [
+            "Water plants rec:1d",
+            "Backups rec:2w",
+            "Rent rec:1m",
+            "Taxes rec:1y",
+            "Standup rec:1b",
+        ] {
+            assert_eq!(round_trip(line), line);
+        }
+        assert_eq!(
+            task("Rent rec:m").recurrence.unwrap().to_string(),
+            "every month"
+        );
+        assert_eq!(round_trip("Rent rec:+3m"), "Rent rec:3m");
+        assert_eq!(
+            task("Standup rec:1b").recurrence.unwrap().to_string(),
+            "every weekday"
+        );
+        // Rules rec: cannot say are written as RRULEs, which read back too.
+        let line = "Gym rrule:FREQ=WEEKLY;BYDAY=MO,TH";
+        assert_eq!(round_trip("Gym every mon,thu"), line);
+        assert_eq!(round_trip(line), line);
+        for bad in ["Rent rec:0d", "Rent rec:2b", "Rent rec:1q", "Rent rec:"] {
+            assert!(Task::from_todo_txt(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn todo_txt_escaping() {
+        let mut task = task("placeholder");
+        task.title = "Line one\nstill one\r\nline two\twith tab \\ backslash".to_string();
+        let line = task.to_todo_txt();
+        assert_eq!(line, "Line one still one  line two\twith tab \\ backslash");
+        assert_eq!(Task::from_todo_txt(&line).unwrap().title, line);
+    }
+
+    #[test]
+    fn todo_txt_keeps_unknown_tags_in_the_title() {
+        let line =
+            "(B) 2026-10-01 Read paper t:2026-10-05 url:https://example.com/a pri:x +research";
+        let task = task(line);
+        assert_eq!(
+            task.title,
+            "Read paper t:2026-10-05 url:https://example.com/a pri:x +research"
+        );
+        assert_eq!((task.due, task.recurrence.as_ref()), (None, None));
+        assert_eq!(task.to_todo_txt(), line);
+    }
+
+    #[test]
+    fn apply_text_rejects_bad_dates() {
+        let mut task = task("Pay rent");
+        assert!(matches!(
+            task.apply_text("Pay rent due:2026-02-30"),
+            Err(Error::InvalidInput(_))
+        ));
+        // Years that would overflow the day count are refused, not a panic.
+        assert!(matches!(
+            task.apply_text("Pay rent due:9223372036854775807-01-01"),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn date_parse_bounds() {
+        assert_eq!(
+            Date::parse("0000-01-01"),
+            Some(Date {
+                year: 0,
+                month: 1,
+                day: 1
+            })
+        );
+        assert!(Date::parse("9999-12-31").is_some());
+        assert_eq!(Date::parse("10000-01-01"), None);
+        assert_eq!(Date::parse("9223372036854775807-01-01"), None);
+        assert_eq!(Date::parse("2026-13-01"), None);
+        assert_eq!(Date::parse("2026-04-31"), None);
+        assert_eq!(Date::parse("2026-01-00"), None);
+        assert!(Date::parse("2024-02-29").is_some());
+        assert_eq!(Date::parse("2100-02-29"), None);
+        assert!(Date::parse("2000-02-29").is_some());
+        assert_eq!(Timestamp::parse("99999-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn add_months_clamps_to_the_month_end() {
+        let date = |text| Date::parse(text).unwrap();
+        assert_eq!(date("2026-01-31").add_months(1), date("2026-02-28"));
+        assert_eq!(date("2024-01-31").add_months(1), date("2024-02-29"));
+        assert_eq!(date("2026-01-31").add_months(3), date("2026-04-30"));
+        assert_eq!(date("2026-11-30").add_months(-12), date("2025-11-30"));
+        // Past the last year `parse` accepts, without looping or panicking.
+        let far = date("9999-12-31").add_months(2);
+        assert_eq!((far.year, far.month, far.day), (10000, 2, 29));
+    }
+}
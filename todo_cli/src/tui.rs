@@ -0,0 +1,556 @@
+//! The full-screen terminal interface behind `todo_cli tui`.
+
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ratatui::backend::{Backend, TestBackend};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use todo_cli::task::join_ids;
+use todo_cli::{Error, FileStorage, Result, Task, TaskStore};
+
+use crate::human_line;
+
+/// What the bottom line of the terminal UI is asking for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Add,
+    Edit(usize),
+    Filter,
+}
+
+/// Text being typed on the bottom line.
+#[derive(Debug)]
+struct Input {
+    prompt: Prompt,
+    text: String,
+    /// Cursor position, in chars.
+    cursor: usize,
+}
+
+/// State of the terminal UI. Each change opens the store afresh, so the
+/// todo file is locked only while one change is saved and one-shot commands
+/// can run alongside.
+struct App {
+    path: PathBuf,
+    /// The tasks as last loaded, in list order.
+    tasks: Vec<Task>,
+    /// Words a task's title must all contain, ignoring case, to be shown.
+    filter: String,
+    /// Index into the visible tasks.
+    selected: usize,
+    input: Option<Input>,
+    /// Outcome of the last change, shown on the bottom line.
+    status: String,
+    quit: bool,
+}
+
+impl App {
+    fn open(path: PathBuf) -> Result<App> {
+        let store = TaskStore::open(FileStorage::open(&path)?)?;
+        Ok(App {
+            path,
+            tasks: store.tasks().to_vec(),
+            filter: String::new(),
+            selected: 0,
+            input: None,
+            // An upgraded file says so on the bottom line.
+            status: store.notices().join("; "),
+            quit: false,
+        })
+    }
+
+    /// The tasks matching the filter being typed or, failing that, the
+    /// current one.
+    fn visible(&self) -> Vec<&Task> {
+        let filter = match &self.input {
+            Some(input) if input.prompt == Prompt::Filter => &input.text,
+            _ => &self.filter,
+        };
+        let words: Vec<String> = filter.split_whitespace().map(str::to_lowercase).collect();
+        self.tasks
+            .iter()
+            .filter(|task| {
+                let title = task.title.to_lowercase();
+                words.iter().all(|word| title.contains(word.as_str()))
+            })
+            .collect()
+    }
+
+    fn selected_task(&self) -> Option<&Task> {
+        self.visible().get(self.selected).copied()
+    }
+
+    /// Keep the selection on a visible task.
+    fn clamp_selection(&mut self) {
+        self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+    }
+
+    /// Run `change` against a freshly opened store and show its message,
+    /// or the error it failed with.
+    fn change(&mut self, change: impl FnOnce(&mut TaskStore<FileStorage>) -> Result<String>) {
+        let result = FileStorage::open(&self.path)
+            .and_then(TaskStore::open)
+            .and_then(|mut store| {
+                let message = change(&mut store);
+                self.tasks = store.tasks().to_vec();
+                message
+            });
+        self.status = result.unwrap_or_else(|e| e.to_string());
+        self.clamp_selection();
+    }
+
+    /// Pick up changes made by other processes.
+    fn reload(&mut self) {
+        self.change(|_| Ok(String::new()));
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+        } else if self.input.is_some() {
+            self.handle_input_key(key);
+        } else {
+            self.handle_list_key(key);
+        }
+    }
+
+    fn handle_list_key(&mut self, key: KeyEvent) {
+        let last = self.visible().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if self.filter.is_empty() => self.quit = true,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.clamp_selection();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Char('a') => self.start_input(Prompt::Add, String::new()),
+            KeyCode::Char('/') => self.start_input(Prompt::Filter, self.filter.clone()),
+            KeyCode::Char('r') => {
+                self.reload();
+                self.status = "Reloaded".to_string();
+            }
+            KeyCode::Char(' ' | 'x' | 'e' | 'd') | KeyCode::Enter => {
+                let Some(task) = self.selected_task() else {
+                    return;
+                };
+                let (id, done) = (task.id, task.done);
+                match key.code {
+                    KeyCode::Char('e') | KeyCode::Enter => {
+                        let text = task.text();
+                        self.start_input(Prompt::Edit(id), text);
+                    }
+                    KeyCode::Char('d') => self.change(|store| {
+                        let task = store.remove(id)?;
+                        Ok(format!("Removed task {}: \"{}\"", task.id, task.title))
+                    }),
+                    _ => self.change(|store| {
+                        let completion = store.set_done(id, !done, false)?;
+                        let task = completion.task;
+                        let verb = if task.done { "Completed" } else { "Reopened" };
+                        let mut message = format!("{} task {}: \"{}\"", verb, task.id, task.title);
+                        if let Some(next) = completion.next {
+                            message.push_str(&format!("; next is task {}", next.id));
+                        }
+                        if !completion.parents.is_empty() {
+                            let ids: Vec<usize> = completion.parents.iter().map(|t| t.id).collect();
+                            message
+                                .push_str(&format!("; parent task {} too", join_ids(&ids, ", ")));
+                        }
+                        Ok(message)
+                    }),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn start_input(&mut self, prompt: Prompt, text: String) {
+        let cursor = text.chars().count();
+        self.input = Some(Input {
+            prompt,
+            text,
+            cursor,
+        });
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+        let byte_at = |text: &str, cursor: usize| {
+            text.char_indices()
+                .nth(cursor)
+                .map_or(text.len(), |(i, _)| i)
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                input.text.insert(byte_at(&input.text, input.cursor), c);
+                input.cursor += 1;
+            }
+            KeyCode::Backspace if input.cursor > 0 => {
+                input.cursor -= 1;
+                input.text.remove(byte_at(&input.text, input.cursor));
+            }
+            KeyCode::Delete if input.cursor < input.text.chars().count() => {
+                input.text.remove(byte_at(&input.text, input.cursor));
+            }
+            KeyCode::Left => input.cursor = input.cursor.saturating_sub(1),
+            KeyCode::Right => input.cursor = (input.cursor + 1).min(input.text.chars().count()),
+            KeyCode::Home => input.cursor = 0,
+            KeyCode::End => input.cursor = input.text.chars().count(),
+            KeyCode::Esc => {
+                if input.prompt == Prompt::Filter {
+                    self.filter.clear();
+                }
+                self.input = None;
+            }
+            KeyCode::Enter => {
+                let Some(Input { prompt, text, .. }) = self.input.take() else {
+                    return;
+                };
+                match prompt {
+                    Prompt::Filter => self.filter = text,
+                    _ if text.trim().is_empty() => {}
+                    Prompt::Add => {
+                        self.change(|store| {
+                            let task = store.add(&text)?;
+                            Ok(format!("Added task {}: \"{}\"", task.id, task.title))
+                        });
+                        // New tasks go to the bottom of the list.
+                        self.selected = self.visible().len().saturating_sub(1);
+                    }
+                    Prompt::Edit(id) => self.change(|store| {
+                        let task = store.edit(id, &text)?;
+                        Ok(format!("Edited task {}: \"{}\"", task.id, task.title))
+                    }),
+                }
+            }
+            _ => {}
+        }
+        self.clamp_selection();
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [list_area, line_area, help_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let visible = self.visible();
+        let mut title = format!(" {} ", self.path.display());
+        if !self.filter.is_empty() {
+            title.push_str(&format!("[filter: {}] ", self.filter));
+        }
+        let items: Vec<ListItem> = visible
+            .iter()
+            .map(|task| ListItem::new(human_line(task)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().reversed())
+            .highlight_symbol("> ");
+        let mut state =
+            ListState::default().with_selected((!visible.is_empty()).then_some(self.selected));
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        match &self.input {
+            Some(input) => {
+                let label = match input.prompt {
+                    Prompt::Add => "Add: ".to_string(),
+                    Prompt::Edit(id) => format!("Edit {}: ", id),
+                    Prompt::Filter => "Filter: ".to_string(),
+                };
+                let column = (label.chars().count() + input.cursor) as u16;
+                frame.render_widget(Paragraph::new(label + &input.text), line_area);
+                frame.set_cursor_position((line_area.x + column, line_area.y));
+            }
+            None => frame.render_widget(Paragraph::new(self.status.as_str()), line_area),
+        }
+        let help = "j/k move  space done  a add  e edit  d delete  / filter  r reload  q quit";
+        frame.render_widget(Paragraph::new(help).dim(), help_area);
+    }
+}
+
+/// What happened next in a terminal UI session.
+enum Next {
+    Key(KeyEvent),
+    /// Nothing for a while; a good moment to reload.
+    Idle,
+    /// No more input; only scripted sessions end this way.
+    End,
+}
+
+/// Where the terminal UI reads keys from.
+trait KeySource {
+    fn next(&mut self) -> io::Result<Next>;
+}
+
+/// Keys typed into the real terminal.
+struct TerminalKeys;
+
+impl KeySource for TerminalKeys {
+    fn next(&mut self) -> io::Result<Next> {
+        if !event::poll(Duration::from_secs(2))? {
+            return Ok(Next::Idle);
+        }
+        match event::read()? {
+            Event::Key(key) => Ok(Next::Key(key)),
+            // Resizes and the like only need a redraw.
+            _ => Ok(Next::Idle),
+        }
+    }
+}
+
+/// Keys given up front, for running the terminal UI from scripts and tests.
+struct ScriptedKeys(VecDeque<KeyEvent>);
+
+impl ScriptedKeys {
+    /// Parse plain characters and named keys like `<Enter>`.
+    fn parse(script: &str) -> Result<ScriptedKeys> {
+        let mut keys = VecDeque::new();
+        let mut rest = script;
+        while let Some(c) = rest.chars().next() {
+            let code = match rest.strip_prefix('<').and_then(|r| r.split_once('>')) {
+                Some((name, after)) if c == '<' => {
+                    rest = after;
+                    match name {
+                        "Enter" => KeyCode::Enter,
+                        "Space" => KeyCode::Char(' '),
+                        "Esc" => KeyCode::Esc,
+                        "Up" => KeyCode::Up,
+                        "Down" => KeyCode::Down,
+                        "Left" => KeyCode::Left,
+                        "Right" => KeyCode::Right,
+                        "Home" => KeyCode::Home,
+                        "End" => KeyCode::End,
+                        "Backspace" => KeyCode::Backspace,
+                        "Delete" => KeyCode::Delete,
+                        "lt" => KeyCode::Char('<'),
+                        _ => {
+                            return Err(Error::InvalidInput(format!("Unknown key <{}>", name)));
+                        }
+                    }
+                }
+                _ => {
+                    rest = &rest[c.len_utf8()..];
+                    KeyCode::Char(c)
+                }
+            };
+            keys.push_back(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+        Ok(ScriptedKeys(keys))
+    }
+}
+
+impl KeySource for ScriptedKeys {
+    fn next(&mut self) -> io::Result<Next> {
+        Ok(self.0.pop_front().map_or(Next::End, Next::Key))
+    }
+}
+
+/// Open the terminal UI on the todo file at `path`. With `keys`, run it on
+/// an 80x24 screen in memory instead and print that screen at the end.
+pub(crate) fn tui(path: PathBuf, keys: Option<&str>) -> Result<()> {
+    let terminal_error = |e| Error::io("<terminal>", e);
+    let mut app = App::open(path)?;
+    match keys {
+        Some(keys) => {
+            let mut keys = ScriptedKeys::parse(keys)?;
+            let mut terminal = Terminal::new(TestBackend::new(80, 24)).map_err(terminal_error)?;
+            run_tui(&mut terminal, &mut app, &mut keys)?;
+            for line in screen(terminal.backend()) {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+        None => {
+            let mut terminal = ratatui::try_init().map_err(terminal_error)?;
+            let result = run_tui(&mut terminal, &mut app, &mut TerminalKeys);
+            ratatui::restore();
+            result
+        }
+    }
+}
+
+/// The lines of an in-memory screen, without trailing spaces.
+fn screen(backend: &TestBackend) -> Vec<String> {
+    let buffer = backend.buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Draw and handle keys until the user quits or the keys run out.
+fn run_tui<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    keys: &mut dyn KeySource,
+) -> Result<()> {
+    let terminal_error = |e| Error::io("<terminal>", e);
+    while !app.quit {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(terminal_error)?;
+        match keys.next().map_err(terminal_error)? {
+            Next::Key(key) => app.handle_key(key),
+            Next::Idle => app.reload(),
+            Next::End => break,
+        }
+    }
+    terminal
+        .draw(|frame| app.draw(frame))
+        .map_err(terminal_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::{env, fs, process};
+
+    use super::*;
+
+    /// A todo file in a fresh directory of its own.
+    fn todo_file(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("todo_cli-tui-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("todo.txt")
+    }
+
+    fn add(path: &Path, texts: &[&str]) {
+        let mut store = TaskStore::open(FileStorage::open(path).unwrap()).unwrap();
+        for text in texts {
+            store.add(text).unwrap();
+        }
+    }
+
+    /// Run the UI on `path` with `keys`, returning the app and the final
+    /// screen.
+    fn run(path: &Path, keys: &str) -> (App, Vec<String>) {
+        let mut app = App::open(path.to_path_buf()).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut keys = ScriptedKeys::parse(keys).unwrap();
+        run_tui(&mut terminal, &mut app, &mut keys).unwrap();
+        let screen = screen(terminal.backend());
+        (app, screen)
+    }
+
+    /// A line of the task list, inside its border.
+    fn row(text: &str) -> String {
+        format!("│{:<78}│", text)
+    }
+
+    /// The tasks saved in the todo file.
+    fn saved(path: &Path) -> Vec<Task> {
+        TaskStore::open(FileStorage::open(path).unwrap())
+            .unwrap()
+            .tasks()
+            .to_vec()
+    }
+
+    #[test]
+    fn add_saves_and_selects_the_new_task() {
+        let path = todo_file("add");
+        add(&path, &["Existing task"]);
+        let (app, screen) = run(&path, "aBuy milk +home<Enter>q");
+        let tasks = saved(&path);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[1].title, "Buy milk +home");
+        assert_eq!(app.selected, 1);
+        assert_eq!(screen[1], row("  1: [ ] Existing task"));
+        assert_eq!(screen[2], row("> 2: [ ] Buy milk +home"));
+        assert_eq!(screen[22], "Added task 2: \"Buy milk +home\"");
+    }
+
+    #[test]
+    fn edit_starts_from_the_task_text() {
+        let path = todo_file("edit");
+        add(&path, &["(A) Renew certificates due:2026-11-01"]);
+        // Drop the "(A) " the prompt starts with and add a tag at the end.
+        let (_, screen) = run(
+            &path,
+            "e<Home><Delete><Delete><Delete><Delete><End> +infra<Enter>q",
+        );
+        let task = &saved(&path)[0];
+        assert_eq!(task.title, "Renew certificates +infra");
+        assert_eq!(task.priority, None);
+        assert_eq!(task.due, todo_cli::Date::parse("2026-11-01"));
+        assert_eq!(screen[22], "Edited task 1: \"Renew certificates +infra\"");
+    }
+
+    #[test]
+    fn edit_prompt_shows_the_text() {
+        let path = todo_file("edit-prompt");
+        add(&path, &["(B) Water plants due:2026-10-20 every week"]);
+        let (app, screen) = run(&path, "e");
+        assert!(app.input.is_some());
+        assert_eq!(
+            screen[22],
+            "Edit 1: (B) Water plants due:2026-10-20 every week"
+        );
+    }
+
+    #[test]
+    fn toggle_marks_done_and_open_again() {
+        let path = todo_file("toggle");
+        add(&path, &["One", "Two"]);
+        run(&path, "j<Space>q");
+        let done: Vec<bool> = saved(&path).iter().map(|task| task.done).collect();
+        assert_eq!(done, [false, true]);
+
+        let (_, screen) = run(&path, "jxq");
+        assert!(!saved(&path)[1].done);
+        assert_eq!(screen[22], "Reopened task 2: \"Two\"");
+    }
+
+    #[test]
+    fn quit_ignores_the_keys_after_it() {
+        let path = todo_file("quit");
+        add(&path, &["One"]);
+        let (app, _) = run(&path, "qaTwo<Enter>");
+        assert!(app.quit);
+        assert_eq!(saved(&path).len(), 1);
+
+        // Esc clears a filter before it quits.
+        let (app, screen) = run(&path, "/nothing<Enter><Esc>");
+        assert!(!app.quit && app.filter.is_empty());
+        assert_eq!(screen[1], row("> 1: [ ] One"));
+        assert!(run(&path, "<Esc>").0.quit);
+    }
+
+    #[test]
+    fn unknown_key_names_are_refused() {
+        assert!(matches!(
+            ScriptedKeys::parse("a<Tab>"),
+            Err(Error::InvalidInput(_))
+        ));
+        let keys = ScriptedKeys::parse("<lt>x<Space>").unwrap().0;
+        let codes: Vec<KeyCode> = keys.iter().map(|key| key.code).collect();
+        assert_eq!(
+            codes,
+            [KeyCode::Char('<'), KeyCode::Char('x'), KeyCode::Char(' ')]
+        );
+    }
+}
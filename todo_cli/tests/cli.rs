@@ -0,0 +1,180 @@
+//! The `todo_cli` binary run against todo files in a temporary directory.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::{env, fs, process};
+
+use todo_cli::{FileStorage, TaskStore};
+
+/// A fresh directory for one test.
+fn scratch(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("todo_cli-cli-{}-{}", process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `todo_cli --file <file> <args>`, with no other todo file in reach.
+fn command(file: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_todo_cli"));
+    command
+        .arg("--file")
+        .arg(file)
+        .args(args)
+        .env_remove("TODO_FILE")
+        .env("XDG_DATA_HOME", file.parent().unwrap());
+    command
+}
+
+fn run(file: &Path, args: &[&str]) -> Output {
+    command(file, args).output().unwrap()
+}
+
+/// The exit code and standard error of a failed run.
+fn failure(file: &Path, args: &[&str]) -> (i32, String) {
+    let output = run(file, args);
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    (output.status.code().unwrap(), stderr)
+}
+
+#[test]
+fn concurrent_adds_all_land_with_unique_ids() {
+    const PROCESSES: usize = 16;
+    let file = scratch("concurrent").join("todo.txt");
+    let children: Vec<_> = (0..PROCESSES)
+        .map(|n| {
+            command(&file, &["add", &format!("Task {}", n)])
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for child in children {
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let store = TaskStore::open(FileStorage::open(&file).unwrap()).unwrap();
+    let mut ids: Vec<usize> = store.tasks().iter().map(|task| task.id).collect();
+    ids.sort();
+    assert_eq!(ids, (1..=PROCESSES).collect::<Vec<_>>());
+    let mut titles: Vec<&str> = store.tasks().iter().map(|t| t.title.as_str()).collect();
+    titles.sort();
+    titles.dedup();
+    assert_eq!(titles.len(), PROCESSES);
+    assert!(!file.with_extension("txt.tmp").exists());
+}
+
+#[test]
+fn errors_exit_with_their_codes() {
+    let file = scratch("exit-codes").join("todo.txt");
+    assert!(run(&file, &["add", "Pay rent"]).status.success());
+    assert!(run(&file, &["add", "Book flights"]).status.success());
+    assert!(run(&file, &["link", "1", "blocks", "2"]).status.success());
+
+    assert_eq!(
+        failure(&file, &["done", "9"]),
+        (3, "todo_cli: No task with id 9\n".to_string())
+    );
+    assert_eq!(
+        failure(&file, &["done", "2"]),
+        (
+            6,
+            "todo_cli: Task 2 is blocked by open task(s) 1; use --force to complete it anyway\n"
+                .to_string()
+        )
+    );
+    let (code, stderr) = failure(&file, &["done", "first"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("invalid value 'first'"), "{}", stderr);
+    let (code, stderr) = failure(&file, &["add", "Call the bank due:2026-02-30"]);
+    assert_eq!(code, 2);
+    assert!(stderr.starts_with("todo_cli: "), "{}", stderr);
+}
+
+#[test]
+fn a_malformed_file_exits_4_with_the_line() {
+    let file = scratch("malformed").join("todo.txt");
+    fs::write(
+        &file,
+        "# todo_cli v8\n# next_id 3\n\
+         1\tfalse\t-\t-\t-\t-\t-\t-\t-\t-\tPay rent\n\
+         2\tmaybe\t-\t-\t-\t-\t-\t-\t-\t-\tBook flights\n",
+    )
+    .unwrap();
+    let (code, stderr) = failure(&file, &["list"]);
+    assert_eq!(code, 4);
+    assert_eq!(
+        stderr,
+        format!(
+            "todo_cli: {}:4: invalid done flag \"maybe\"\n",
+            file.display()
+        )
+    );
+}
+
+/// `list` in `format`, over a file with a title that needs quoting.
+fn list_as(format: &str) -> String {
+    let file = scratch(&format!("format-{}", format)).join("todo.txt");
+    fs::write(
+        &file,
+        "# todo_cli v8\n# next_id 4\n\
+         1\tfalse\tA\t2026-11-01\t2026-10-01T09:00:00Z\t-\t-\t2026-10-01T09:00:00Z\t-\t-\t\
+         Say \"hi\", then leave +home\n\
+         2\ttrue\t-\t-\t2026-10-02T10:30:00Z\t2026-10-03T11:00:00Z\tFREQ=WEEKLY\t\
+         2026-10-03T11:00:00Z\t1\t-\tBook flights\n\
+         3\tfalse\t-\t-\t-\t-\t-\t-\t-\t2\tCall the bank\n",
+    )
+    .unwrap();
+    let args = match format {
+        "porcelain" => vec!["--porcelain", "list"],
+        _ => vec!["--format", format, "list"],
+    };
+    let output = run(&file, &args);
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn list_formats_are_stable() {
+    assert_eq!(
+        list_as("json"),
+        concat!(
+            r#"[{"id":1,"title":"Say \"hi\", then leave +home","done":false,"priority":"A","#,
+            r#""due":"2026-11-01","created":"2026-10-01T09:00:00Z","completed":null,"#,
+            r#""recurrence":null,"parent":null,"blocked_by":[],"tags":["+home"]},"#,
+            r#"{"id":2,"title":"Book flights","done":true,"priority":null,"due":null,"#,
+            r#""created":"2026-10-02T10:30:00Z","completed":"2026-10-03T11:00:00Z","#,
+            r#""recurrence":"every week","parent":1,"blocked_by":[],"tags":[]},"#,
+            r#"{"id":3,"title":"Call the bank","done":false,"priority":null,"due":null,"#,
+            r#""created":null,"completed":null,"recurrence":null,"parent":null,"#,
+            r#""blocked_by":[2],"tags":[]}]"#,
+            "\n"
+        )
+    );
+    assert_eq!(
+        list_as("csv"),
+        "id,title,done,priority,due,created,completed,recurrence,parent,blocked_by\n\
+         1,\"Say \"\"hi\"\", then leave +home\",false,A,2026-11-01,2026-10-01T09:00:00Z,,,,\n\
+         2,Book flights,true,,,2026-10-02T10:30:00Z,2026-10-03T11:00:00Z,every week,1,\n\
+         3,Call the bank,false,,,,,,,2\n"
+    );
+    assert_eq!(
+        list_as("table"),
+        "ID  DONE  PRI  DUE         TITLE\n\
+         1         A    2026-11-01  Say \"hi\", then leave +home\n\
+         2   x                      Book flights\n\
+         3                          Call the bank\n"
+    );
+    assert_eq!(
+        list_as("porcelain"),
+        "1\topen\tA\t2026-11-01\t2026-10-01T09:00:00Z\t-\tSay \"hi\", then leave +home\n\
+         2\tdone\t-\t-\t2026-10-02T10:30:00Z\t2026-10-03T11:00:00Z\tBook flights\n\
+         3\topen\t-\t-\t-\t-\tCall the bank\n"
+    );
+}
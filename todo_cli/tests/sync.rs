@@ -0,0 +1,148 @@
+//! `sync` against real git repositories in a temporary directory.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, process};
+
+use todo_cli::{sync, FileStorage, Task, TaskStore};
+
+/// A fresh directory for one test.
+fn scratch(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("todo_cli-sync-{}-{}", process::id(), test));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {}: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Set who commits in the repository at `dir`, whatever the global config.
+fn configure(dir: &Path, name: &str) {
+    git(dir, &["config", "user.name", name]);
+    git(
+        dir,
+        &["config", "user.email", &format!("{}@example.com", name)],
+    );
+    git(dir, &["config", "commit.gpgsign", "false"]);
+}
+
+/// Clone `remote` into `dir` and return the todo file in the clone.
+fn clone(remote: &Path, dir: &Path, name: &str) -> PathBuf {
+    git(
+        remote.parent().unwrap(),
+        &[
+            "clone",
+            "--quiet",
+            remote.to_str().unwrap(),
+            dir.to_str().unwrap(),
+        ],
+    );
+    configure(dir, name);
+    dir.join("todo.txt")
+}
+
+fn store(path: &Path) -> TaskStore<FileStorage> {
+    TaskStore::open(FileStorage::open(path).unwrap()).unwrap()
+}
+
+fn tasks(path: &Path) -> Vec<Task> {
+    store(path).tasks().to_vec()
+}
+
+#[test]
+fn sync_shares_and_merges_changes_through_a_bare_remote() {
+    let dir = scratch("bare");
+    let remote = dir.join("todo.git");
+    git(
+        &dir,
+        &["init", "--quiet", "--bare", remote.to_str().unwrap()],
+    );
+    let alice = clone(&remote, &dir.join("alice"), "alice");
+    let bob = clone(&remote, &dir.join("bob"), "bob");
+
+    store(&alice).add("Pay rent").unwrap();
+    store(&alice).add("Book flights").unwrap();
+    let done = sync(&alice).unwrap();
+    assert_eq!(done[0], "Committed local changes");
+    assert!(
+        done[1].starts_with("Pushed to new branch origin/"),
+        "{:?}",
+        done
+    );
+
+    let done = sync(&bob).unwrap();
+    assert!(
+        done[0].starts_with("Fast-forwarded to origin/"),
+        "{:?}",
+        done
+    );
+    assert_eq!(tasks(&bob), tasks(&alice));
+
+    // Both sides change the same task, and each adds one under id 3.
+    store(&alice).edit(1, "(A) Pay rent").unwrap();
+    store(&alice).add("Alice's task").unwrap();
+    store(&bob).set_done(1, true, false).unwrap();
+    store(&bob).add("Bob's task").unwrap();
+    sync(&alice).unwrap();
+    let done = sync(&bob).unwrap();
+    assert!(
+        done.iter().any(|step| step.starts_with("Merged ")),
+        "{:?}",
+        done
+    );
+
+    let merged = tasks(&bob);
+    assert_eq!(merged[0].priority, Some('A'));
+    assert!(merged[0].done);
+    let titles: Vec<(usize, &str)> = merged.iter().map(|t| (t.id, t.title.as_str())).collect();
+    assert_eq!(
+        titles,
+        [
+            (1, "Pay rent"),
+            (2, "Book flights"),
+            (3, "Alice's task"),
+            (4, "Bob's task")
+        ]
+    );
+
+    sync(&alice).unwrap();
+    assert_eq!(tasks(&alice), merged);
+    assert_eq!(sync(&alice).unwrap(), ["Already up to date"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn sync_pushes_to_a_local_upstream_branch() {
+    let dir = scratch("local");
+    git(&dir, &["init", "--quiet", "-b", "main"]);
+    configure(&dir, "alice");
+    let todo = dir.join("todo.txt");
+    store(&todo).add("Pay rent").unwrap();
+    git(&dir, &["add", "todo.txt"]);
+    git(&dir, &["commit", "--quiet", "-m", "Start a list"]);
+    git(&dir, &["checkout", "--quiet", "-b", "work"]);
+    git(&dir, &["branch", "--quiet", "-u", "main"]);
+
+    store(&todo).add("Book flights").unwrap();
+    let done = sync(&todo).unwrap();
+    assert_eq!(done, ["Committed local changes", "Pushed to main"]);
+    assert_eq!(
+        git(&dir, &["rev-parse", "main"]),
+        git(&dir, &["rev-parse", "work"])
+    );
+    let _ = fs::remove_dir_all(&dir);
+}